@@ -0,0 +1,108 @@
+//! Compares sending through `Sender`, which serializes into a reused buffer, with serializing
+//! every message into a freshly allocated `Vec`, which is what `Sender` used to do.
+//!
+//! Run with `cargo bench --bench send`.
+
+extern crate tcp_channel;
+extern crate serde;
+#[macro_use] extern crate serde_derive;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::{self, BufWriter, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use byteorder::WriteBytesExt;
+use tcp_channel::{BigEndian, ChannelSend, Endian, SenderBuilder};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Serialize)]
+struct Telemetry {
+    id: u32,
+    label: &'static str,
+    values: [f64; 4],
+}
+
+#[derive(Serialize)]
+struct Blob {
+    data: Vec<u8>,
+}
+
+const MESSAGES: usize = 200_000;
+
+fn measure<F: FnMut()>(name: &str, bytes_per_message: usize, mut send_one: F) {
+    // Warm up, so that buffers have reached their final size.
+    for _ in 0..1000 {
+        send_one();
+    }
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..MESSAGES {
+        send_one();
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+    println!(
+        "{:<32} {:>10.1} ns/message {:>10.1} MiB/s {:>6.2} allocations/message",
+        name,
+        nanos(elapsed) / MESSAGES as f64,
+        (bytes_per_message * MESSAGES) as f64 / elapsed.as_secs_f64() / 1_048_576.0,
+        allocations as f64 / MESSAGES as f64,
+    );
+}
+fn nanos(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1e9
+}
+
+fn compare<T: serde::Serialize>(name: &str, value: &T) {
+    let config = BigEndian::config();
+    let size = config.serialize(value).unwrap().len() + 8;
+
+    let mut writer = BufWriter::new(io::sink());
+    measure(&format!("{}, Vec per message", name), size, || {
+        let buffer = config.serialize(value).unwrap();
+        writer.write_u64::<BigEndian>(buffer.len() as u64).unwrap();
+        writer.write_all(&buffer).unwrap();
+    });
+
+    let mut sender = SenderBuilder::buffered()
+        .with_type::<T>()
+        .with_writer::<BufWriter<io::Sink>>()
+        .build(BufWriter::new(io::sink()));
+    measure(&format!("{}, Sender", name), size, || {
+        sender.send(value).unwrap();
+    });
+}
+
+fn main() {
+    compare("telemetry", &Telemetry {
+        id: 42,
+        label: "temperature",
+        values: [21.5, 21.6, 21.4, 21.5],
+    });
+    compare("64 KiB blob", &Blob {
+        data: vec! [0xAB; 65_536],
+    });
+}
@@ -0,0 +1,397 @@
+//! Procedural macros for tcp-channel, re-exported by it with the `derive` feature.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Fields, FnArg, Ident, ItemTrait, Pat, ReturnType, TraitItem, TraitItemFn, Type};
+
+/// Define a request/response protocol from a trait.
+///
+/// Every method of the trait becomes a call, which takes its arguments by value and returns a
+/// response. For a trait `Foo`, this generates:
+///
+/// - `FooRequest`, an enum with one variant per method, holding its arguments,
+/// - `FooResponse`, an enum with one variant per method, holding its return value,
+/// - `FooClient<S, R>`, which has one method per call, sending the request through `S` and
+///   waiting for the response from `R`; `FooClient::connect` uses a `Sender` and a `Receiver`
+///   over TCP,
+/// - `FooServer`, implemented for every implementation of `Foo`, which answers requests using
+///   its methods.
+///
+/// The methods must take `&self` or `&mut self`, and the arguments and return values must
+/// implement `Debug`, `Serialize` and `Deserialize`.
+#[proc_macro_attribute]
+pub fn protocol(args: TokenStream, input: TokenStream) -> TokenStream {
+    if !args.is_empty() {
+        let args = TokenStream2::from(args);
+        return syn::Error::new_spanned(args, "`protocol` takes no arguments").to_compile_error().into()
+    }
+    let item = parse_macro_input!(input as ItemTrait);
+    match expand(&item) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+// A method of the protocol trait.
+struct Call<'a> {
+    method: &'a Ident,
+    variant: Ident,
+    docs: Vec<&'a Attribute>,
+    args: Vec<(Ident, &'a Type)>,
+    output: Option<&'a Type>,
+}
+
+fn parse_call(method: &TraitItemFn) -> syn::Result<Call<'_>> {
+    let signature = &method.sig;
+    if !signature.generics.params.is_empty() || signature.generics.where_clause.is_some() {
+        return Err(syn::Error::new_spanned(&signature.generics, "protocol methods can't be generic"))
+    }
+    if let Some(asyncness) = signature.asyncness {
+        return Err(syn::Error::new_spanned(asyncness, "protocol methods can't be async"))
+    }
+
+    let mut inputs = signature.inputs.iter();
+    match inputs.next() {
+        Some(FnArg::Receiver(receiver)) if receiver.reference.is_some() => (),
+        _ => return Err(syn::Error::new_spanned(signature, "protocol methods must take `&self` or `&mut self`")),
+    }
+    let mut args = Vec::new();
+    for input in inputs {
+        let input = match input {
+            FnArg::Typed(input) => input,
+            FnArg::Receiver(receiver) => return Err(syn::Error::new_spanned(receiver, "unexpected receiver")),
+        };
+        let name = match *input.pat {
+            Pat::Ident(ref pat) if pat.subpat.is_none() => pat.ident.clone(),
+            _ => return Err(syn::Error::new_spanned(&input.pat, "protocol arguments must be plain names")),
+        };
+        args.push((name, owned_type(&input.ty)?));
+    }
+    let output = match signature.output {
+        ReturnType::Default => None,
+        ReturnType::Type(_, ref ty) => Some(owned_type(ty)?),
+    };
+
+    Ok(Call {
+        method: &signature.ident,
+        variant: Ident::new(&camel_case(&signature.ident.to_string()), signature.ident.span()),
+        docs: method.attrs.iter().filter(|attr| attr.path().is_ident("doc")).collect(),
+        args,
+        output,
+    })
+}
+
+// Requests and responses are sent by value, so they can't borrow.
+fn owned_type(ty: &Type) -> syn::Result<&Type> {
+    match ty {
+        Type::Reference(_) => Err(syn::Error::new_spanned(ty, "protocol types can't be references")),
+        ty => Ok(ty),
+    }
+}
+
+// Turn a method name, like `get_value`, into a variant name, like `GetValue`.
+fn camel_case(name: &str) -> String {
+    name.split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map(|first| first.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+        })
+        .collect()
+}
+
+fn expand(item: &ItemTrait) -> syn::Result<TokenStream2> {
+    if !item.generics.params.is_empty() || item.generics.where_clause.is_some() {
+        return Err(syn::Error::new_spanned(&item.generics, "protocol traits can't be generic"))
+    }
+    let mut calls = Vec::new();
+    for trait_item in &item.items {
+        match trait_item {
+            TraitItem::Fn(method) => calls.push(parse_call(method)?),
+            _ => return Err(syn::Error::new_spanned(trait_item, "protocol traits can only contain methods")),
+        }
+    }
+
+    let vis = &item.vis;
+    let name = &item.ident;
+    let request = format_ident!("{}Request", name);
+    let response = format_ident!("{}Response", name);
+    let client = format_ident!("{}Client", name);
+    let server = format_ident!("{}Server", name);
+    let krate = quote!(::tcp_channel);
+    let serde_path = syn::LitStr::new("::tcp_channel::__private::serde", Span::call_site());
+
+    let request_variants = calls.iter().map(|call| {
+        let variant = &call.variant;
+        let types = call.args.iter().map(|(_, ty)| ty);
+        if call.args.is_empty() {
+            quote!(#variant)
+        } else {
+            quote!(#variant(#(#types),*))
+        }
+    });
+    let response_variants = calls.iter().map(|call| {
+        let variant = &call.variant;
+        match call.output {
+            Some(ty) => quote!(#variant(#ty)),
+            None => quote!(#variant),
+        }
+    });
+
+    let client_methods = calls.iter().map(|call| {
+        let Call { method, variant, docs, .. } = call;
+        let names = call.args.iter().map(|(name, _)| name).collect::<Vec<_>>();
+        let types = call.args.iter().map(|(_, ty)| ty);
+        let request_value = if names.is_empty() {
+            quote!(#request::#variant)
+        } else {
+            quote!(#request::#variant(#(#names),*))
+        };
+        let (output, pattern, value) = match call.output {
+            Some(ty) => (quote!(#ty), quote!(#response::#variant(value)), quote!(value)),
+            None => (quote!(()), quote!(#response::#variant), quote!(())),
+        };
+        quote! {
+            #(#docs)*
+            pub fn #method(&mut self, #(#names: #types),*) -> ::std::result::Result<#output, #krate::CallError<S::Error, R::Error>> {
+                #[allow(unreachable_patterns)]
+                match #krate::call(&mut self.sender, &mut self.receiver, &#request_value)? {
+                    #pattern => ::std::result::Result::Ok(#value),
+                    _ => ::std::result::Result::Err(#krate::CallError::UnexpectedResponse),
+                }
+            }
+        }
+    });
+
+    let dispatch_arms = calls.iter().map(|call| {
+        let Call { method, variant, .. } = call;
+        let names = call.args.iter().map(|(name, _)| name).collect::<Vec<_>>();
+        let pattern = if names.is_empty() {
+            quote!(#request::#variant)
+        } else {
+            quote!(#request::#variant(#(#names),*))
+        };
+        match call.output {
+            Some(_) => quote!(#pattern => #response::#variant(#name::#method(self, #(#names),*))),
+            None => quote!(#pattern => {
+                #name::#method(self, #(#names),*);
+                #response::#variant
+            }),
+        }
+    });
+
+    let request_doc = format!("The requests of the `{}` protocol.", name);
+    let response_doc = format!("The responses of the `{}` protocol.", name);
+    let client_doc = format!("A client of the `{}` protocol, sending requests through `S` and receiving responses from `R`.", name);
+    let server_doc = format!("Answers the requests of the `{}` protocol, using the methods of `{}`.", name, name);
+
+    Ok(quote! {
+        #item
+
+        #[doc = #request_doc]
+        #[derive(Debug, #krate::__private::serde::Serialize, #krate::__private::serde::Deserialize)]
+        #[serde(crate = #serde_path)]
+        #vis enum #request {
+            #(#request_variants),*
+        }
+
+        #[doc = #response_doc]
+        #[derive(Debug, #krate::__private::serde::Serialize, #krate::__private::serde::Deserialize)]
+        #[serde(crate = #serde_path)]
+        #vis enum #response {
+            #(#response_variants),*
+        }
+
+        #[doc = #client_doc]
+        #vis struct #client<S, R> {
+            sender: S,
+            receiver: R,
+        }
+        impl<S, R> #client<S, R> {
+            pub fn new(sender: S, receiver: R) -> Self {
+                Self { sender, receiver }
+            }
+            pub fn into_inner(self) -> (S, R) {
+                (self.sender, self.receiver)
+            }
+        }
+        impl #client<
+            #krate::Sender<#request, #krate::BigEndian, ::std::io::BufWriter<::std::net::TcpStream>>,
+            #krate::Receiver<#response, #krate::BigEndian, ::std::io::BufReader<::std::net::TcpStream>>,
+        > {
+            /// Connect to a server over TCP, using the default channel options.
+            pub fn connect<A: ::std::net::ToSocketAddrs>(address: A) -> ::std::io::Result<Self> {
+                let stream = ::std::net::TcpStream::connect(address)?;
+                let peer = stream.peer_addr()?;
+                let sender = #krate::SenderBuilder::buffered()
+                    .with_type::<#request>()
+                    .with_peer_addr(peer)
+                    .build(::std::io::BufWriter::new(stream.try_clone()?));
+                let receiver = #krate::ReceiverBuilder::buffered()
+                    .with_type::<#response>()
+                    .with_peer_addr(peer)
+                    .build(::std::io::BufReader::new(stream));
+                ::std::result::Result::Ok(Self::new(sender, receiver))
+            }
+        }
+        impl<S, R> #client<S, R>
+        where
+            S: #krate::ChannelSend<#request>,
+            R: #krate::ChannelRecv<#response>,
+        {
+            #(#client_methods)*
+        }
+
+        #[doc = #server_doc]
+        #vis trait #server: #name {
+            /// Answer a single request.
+            fn dispatch(&mut self, request: #request) -> #response;
+            /// Answer every request from `receiver` through `sender`, until the client
+            /// disconnects.
+            fn serve<R, S>(&mut self, receiver: R, sender: S) -> ::std::result::Result<(), #krate::ForwardError<R::Error, S::Error>>
+            where
+                R: #krate::ChannelRecv<#request>,
+                S: #krate::ChannelSend<#response>,
+            {
+                #krate::serve(receiver, sender, |request| self.dispatch(request))
+            }
+        }
+        impl<T: #name + ?Sized> #server for T {
+            fn dispatch(&mut self, request: #request) -> #response {
+                match request {
+                    #(#dispatch_arms),*
+                }
+            }
+        }
+    })
+}
+
+/// Derive `MessageKind` for an enum, so that `Router::route` can pass the payload of each variant
+/// to its handler.
+///
+/// For an enum `Foo`, this generates:
+///
+/// - `FooKind`, a field-less copy of `Foo`, which is its `MessageKind::Kind`,
+/// - a module `foo`, named after the enum, with a unit struct per variant implementing `Route`.
+///   The payload of a variant is `()` if it has no fields, the field itself if it has one, and a
+///   tuple of the fields, in order, otherwise.
+///
+/// The enum can't be generic.
+#[proc_macro_derive(MessageKind)]
+pub fn derive_message_kind(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_message_kind(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+// Turn an enum name, like `ClientToServer`, into a module name, like `client_to_server`.
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (index, char) in name.chars().enumerate() {
+        if char.is_uppercase() {
+            if index > 0 {
+                snake.push('_');
+            }
+            snake.extend(char.to_lowercase());
+        } else {
+            snake.push(char);
+        }
+    }
+    snake
+}
+
+fn expand_message_kind(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let data = match input.data {
+        Data::Enum(ref data) => data,
+        _ => return Err(syn::Error::new_spanned(input, "`MessageKind` can only be derived for enums")),
+    };
+    if !input.generics.params.is_empty() || input.generics.where_clause.is_some() {
+        return Err(syn::Error::new_spanned(&input.generics, "`MessageKind` can't be derived for generic enums"))
+    }
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let kind = format_ident!("{}Kind", name);
+    let routes = Ident::new(&snake_case(&name.to_string()), name.span());
+    let krate = quote!(::tcp_channel);
+
+    let variants = data.variants.iter().map(|variant| &variant.ident).collect::<Vec<_>>();
+    let kind_arms = data.variants.iter().map(|variant| {
+        let variant = &variant.ident;
+        quote!(#name::#variant { .. } => #kind::#variant)
+    });
+
+    let route_impls = data.variants.iter().map(|variant| {
+        let ident = &variant.ident;
+        let types = variant.fields.iter().map(|field| &field.ty).collect::<Vec<_>>();
+        let bindings = (0..types.len()).map(|index| format_ident!("field{}", index)).collect::<Vec<_>>();
+        let pattern = match variant.fields {
+            Fields::Unit => quote!(#name::#ident),
+            Fields::Unnamed(_) => quote!(#name::#ident(#(#bindings),*)),
+            Fields::Named(_) => {
+                let names = variant.fields.iter().map(|field| &field.ident);
+                quote!(#name::#ident { #(#names: #bindings),* })
+            },
+        };
+        let (payload, value) = match types.len() {
+            1 => (quote!(#(#types)*), quote!(#(#bindings)*)),
+            _ => (quote!((#(#types),*)), quote!((#(#bindings),*))),
+        };
+        quote! {
+            impl #krate::Route<#name> for #routes::#ident {
+                type Payload = #payload;
+                fn kind(&self) -> #kind {
+                    #kind::#ident
+                }
+                #[allow(unreachable_patterns)]
+                fn payload(&self, message: #name) -> ::std::result::Result<#payload, #name> {
+                    match message {
+                        #pattern => ::std::result::Result::Ok(#value),
+                        message => ::std::result::Result::Err(message),
+                    }
+                }
+            }
+        }
+    });
+    let route_structs = variants.iter().map(|variant| {
+        let doc = format!("The route to `{}::{}`.", name, variant);
+        quote! {
+            #[doc = #doc]
+            #[derive(Clone, Copy, Debug)]
+            pub struct #variant;
+        }
+    });
+
+    let kind_doc = format!("The kinds of `{}`, one per variant.", name);
+    let routes_doc = format!("The routes to the variants of `{}`, for `Router::route`.", name);
+
+    // Not every variant needs a route, nor every program the kinds.
+    Ok(quote! {
+        #[doc = #kind_doc]
+        #[allow(dead_code)]
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        #vis enum #kind {
+            #(#variants),*
+        }
+        impl #krate::MessageKind for #name {
+            type Kind = #kind;
+            fn kind(&self) -> #kind {
+                match *self {
+                    #(#kind_arms),*
+                }
+            }
+        }
+
+        #[doc = #routes_doc]
+        #[allow(dead_code)]
+        #vis mod #routes {
+            #(#route_structs)*
+        }
+        #(#route_impls)*
+    })
+}
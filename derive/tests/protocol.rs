@@ -0,0 +1,135 @@
+extern crate tcp_channel;
+
+use std::io::Cursor;
+use std::net::TcpListener;
+use std::thread;
+
+use tcp_channel::{CallError, ChannelRecv, ChannelSend, ReceiverBuilder, SenderBuilder};
+
+#[tcp_channel::protocol]
+pub trait Counter {
+    /// Add to the counter, returning the new value.
+    fn add(&mut self, amount: u64) -> u64;
+    fn get(&self) -> u64;
+    fn set_label(&mut self, label: String, uppercase: bool);
+    fn label(&self) -> Option<String>;
+    fn reset(&mut self);
+}
+
+#[derive(Default)]
+struct State {
+    count: u64,
+    label: Option<String>,
+}
+impl Counter for State {
+    fn add(&mut self, amount: u64) -> u64 {
+        self.count += amount;
+        self.count
+    }
+    fn get(&self) -> u64 {
+        self.count
+    }
+    fn set_label(&mut self, label: String, uppercase: bool) {
+        self.label = Some(if uppercase { label.to_uppercase() } else { label });
+    }
+    fn label(&self) -> Option<String> {
+        self.label.clone()
+    }
+    fn reset(&mut self) {
+        *self = State::default();
+    }
+}
+
+#[test]
+fn dispatch() {
+    let mut state = State::default();
+
+    match state.dispatch(CounterRequest::Add(5)) {
+        CounterResponse::Add(5) => (),
+        response => panic!("unexpected response: {:?}", response),
+    }
+    match state.dispatch(CounterRequest::SetLabel("apples".into(), true)) {
+        CounterResponse::SetLabel => (),
+        response => panic!("unexpected response: {:?}", response),
+    }
+    match state.dispatch(CounterRequest::Label) {
+        CounterResponse::Label(Some(ref label)) if label == "APPLES" => (),
+        response => panic!("unexpected response: {:?}", response),
+    }
+}
+
+#[test]
+fn tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let (stream, peer) = listener.accept().unwrap();
+        let receiver = ReceiverBuilder::realtime()
+            .with_type::<CounterRequest>()
+            .with_peer_addr(peer)
+            .build(stream.try_clone().unwrap());
+        let sender = SenderBuilder::realtime()
+            .with_type::<CounterResponse>()
+            .with_peer_addr(peer)
+            .build(stream);
+
+        let mut state = State::default();
+        state.serve(receiver, sender).unwrap();
+        state.count
+    });
+
+    let mut client = CounterClient::connect(address).unwrap();
+    assert_eq!(client.add(2).unwrap(), 2);
+    assert_eq!(client.add(40).unwrap(), 42);
+    assert_eq!(client.get().unwrap(), 42);
+    assert_eq!(client.label().unwrap(), None);
+    client.set_label("total".into(), false).unwrap();
+    assert_eq!(client.label().unwrap(), Some("total".into()));
+    client.reset().unwrap();
+    client.add(7).unwrap();
+
+    // The server stops once the client disconnects.
+    drop(client);
+    assert_eq!(server.join().unwrap(), 7);
+}
+
+#[test]
+fn unexpected_response() {
+    // Clients work over any channel, so a recorded response can stand in for a misbehaving
+    // server.
+    let mut responses = Vec::new();
+    SenderBuilder::buffered()
+        .with_type::<CounterResponse>()
+        .with_writer::<&mut Vec<u8>>()
+        .build(&mut responses)
+        .send(&CounterResponse::Get(1))
+        .unwrap();
+
+    let mut requests = Vec::new();
+    {
+        let sender = SenderBuilder::buffered()
+            .with_type::<CounterRequest>()
+            .with_writer::<&mut Vec<u8>>()
+            .build(&mut requests);
+        let receiver = ReceiverBuilder::buffered()
+            .with_type::<CounterResponse>()
+            .with_reader::<Cursor<Vec<u8>>>()
+            .build(Cursor::new(responses));
+        let mut client = CounterClient::new(sender, receiver);
+
+        match client.add(1) {
+            Err(CallError::UnexpectedResponse) => (),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<CounterRequest>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .build(Cursor::new(requests));
+    match receiver.recv() {
+        Ok(CounterRequest::Add(1)) => (),
+        result => panic!("unexpected request: {:?}", result),
+    }
+}
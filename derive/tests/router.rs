@@ -0,0 +1,131 @@
+extern crate tcp_channel;
+extern crate serde;
+#[macro_use] extern crate serde_derive;
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::io::{BufReader, BufWriter};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use tcp_channel::{ChannelRecv, ChannelSend, MessageKind, ReceiverBuilder, RouteError, Router, SenderBuilder};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, MessageKind)]
+enum Request {
+    Add(u32, u32),
+    Echo(String),
+    Scale { value: u32, factor: u32 },
+    Quit,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum Response {
+    Sum(u32),
+    Text(String),
+}
+
+fn router<E: 'static>() -> Router<Request, Response, E> {
+    Router::new()
+        .route(request::Add, |(a, b), reply| reply.send(&Response::Sum(a + b)))
+        .route(request::Echo, |text, reply| {
+            // Handlers may reply any number of times.
+            reply.send(&Response::Text(text.clone()))?;
+            reply.send(&Response::Text(text))
+        })
+        .route(request::Scale, |(value, factor), reply| reply.send(&Response::Sum(value * factor)))
+}
+
+#[test]
+fn kinds() {
+    assert_eq!(Request::Add(1, 2).kind(), RequestKind::Add);
+    assert_eq!(Request::Scale { value: 1, factor: 2 }.kind(), RequestKind::Scale);
+    assert_eq!(Request::Quit.kind(), RequestKind::Quit);
+}
+
+#[test]
+fn dispatch() {
+    let mut requests = VecDeque::from(vec! [
+        Request::Add(1, 2),
+        Request::Echo("hi".into()),
+        Request::Scale { value: 3, factor: 4 },
+    ]);
+    let mut replies = VecDeque::new();
+
+    router::<Infallible>().run(&mut requests, &mut replies).unwrap();
+    assert_eq!(Vec::from(replies), [Response::Sum(3), Response::Text("hi".into()), Response::Text("hi".into()), Response::Sum(12)]);
+
+    // Variants without fields have an empty payload.
+    let mut requests = VecDeque::from(vec! [Request::Quit]);
+    let mut replies = VecDeque::new();
+    router::<Infallible>()
+        .route(request::Quit, |(), reply| reply.send(&Response::Text("bye".into())))
+        .run(&mut requests, &mut replies)
+        .unwrap();
+    assert_eq!(Vec::from(replies), [Response::Text("bye".into())]);
+}
+
+#[test]
+fn unhandled() {
+    let mut requests = VecDeque::from(vec! [Request::Add(1, 2), Request::Quit, Request::Add(3, 4)]);
+    let mut replies = VecDeque::new();
+
+    match router::<Infallible>().run(&mut requests, &mut replies) {
+        Err(RouteError::Unhandled(Request::Quit)) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    assert_eq!(Vec::from(replies), [Response::Sum(3)]);
+    assert_eq!(requests.len(), 1);
+
+    // With a fallback, nothing is unhandled.
+    let mut requests = VecDeque::from(vec! [Request::Quit, Request::Add(3, 4)]);
+    let mut replies = VecDeque::new();
+    router::<Infallible>()
+        .with_fallback(|_, reply| reply.send(&Response::Text("unknown".into())))
+        .run(&mut requests, &mut replies)
+        .unwrap();
+    assert_eq!(Vec::from(replies), [Response::Text("unknown".into()), Response::Sum(7)]);
+
+    // A single message can be dispatched without a loop.
+    let mut replies = VecDeque::new();
+    assert_eq!(router::<Infallible>().handle(Request::Quit, &mut replies).unwrap(), Some(Request::Quit));
+    assert!(replies.is_empty());
+}
+
+#[test]
+fn tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let receiver = ReceiverBuilder::buffered()
+            .with_type::<Request>()
+            .build(BufReader::new(stream.try_clone().unwrap()));
+        let sender = SenderBuilder::buffered()
+            .with_type::<Response>()
+            .build(BufWriter::new(stream));
+        router().run(receiver, sender)
+    });
+
+    let stream = TcpStream::connect(address).unwrap();
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<Response>()
+        .build(BufReader::new(stream.try_clone().unwrap()));
+    let mut sender = SenderBuilder::buffered()
+        .with_type::<Request>()
+        .build(BufWriter::new(stream));
+
+    sender.send(&Request::Add(20, 22)).unwrap();
+    sender.flush().unwrap();
+    assert_eq!(receiver.recv().unwrap(), Response::Sum(42));
+
+    sender.send(&Request::Echo("Hello".into())).unwrap();
+    sender.flush().unwrap();
+    assert_eq!(receiver.recv().unwrap(), Response::Text("Hello".into()));
+    assert_eq!(receiver.recv().unwrap(), Response::Text("Hello".into()));
+
+    // Disconnecting ends the server's loop normally.
+    drop(sender);
+    drop(receiver);
+    server.join().unwrap().unwrap();
+}
@@ -1,4 +1,6 @@
-#[derive(Debug, Serialize, Deserialize)]
+use tcp_channel::MessageKind;
+
+#[derive(Debug, Serialize, Deserialize, MessageKind)]
 pub(crate) enum ClientToServer {
     Say(String),
     Leave,
@@ -5,9 +5,9 @@ extern crate serde;
 use std::net::TcpListener;
 
 mod common;
-use common::{ClientToServer, ServerToClient};
+use common::{client_to_server, ClientToServer, ServerToClient};
 
-use tcp_channel::{ReceiverBuilder, ChannelRecv, SenderBuilder, ChannelSend};
+use tcp_channel::{ReceiverBuilder, SenderBuilder, Router};
 
 fn main() {
     let address = std::env::args().nth(1).unwrap();
@@ -15,19 +15,22 @@ fn main() {
 
     while let Ok((stream, client_address)) = listener.accept() {
         println!("INFO: Started connection with {}", client_address);
-        let mut receiver = ReceiverBuilder::realtime()
+        let receiver = ReceiverBuilder::realtime()
             .with_type::<ClientToServer>()
+            .with_peer_addr(client_address)
             .build(stream.try_clone().unwrap());
 
-        let mut sender = SenderBuilder::realtime()
+        let sender = SenderBuilder::realtime()
             .with_type::<ServerToClient>()
+            .with_peer_addr(client_address)
             .build(stream);
 
-        while let Ok(message) = receiver.recv() {
-            match message {
-                ClientToServer::Say(_) => sender.send(&ServerToClient::Answer("Hi".into())).unwrap(),
-                ClientToServer::Leave => sender.send(&ServerToClient::Answer("Goodbye".into())).unwrap(),
-            }
+        let mut router = Router::new()
+            .route(client_to_server::Say, |_, reply| reply.send(&ServerToClient::Answer("Hi".into())))
+            .route(client_to_server::Leave, |(), reply| reply.send(&ServerToClient::Answer("Goodbye".into())));
+
+        if let Err(error) = router.run(receiver, sender) {
+            println!("ERROR: Connection with {} failed: {}", client_address, error);
         }
     }
     println!("INFO: Stopped server");
@@ -0,0 +1,13 @@
+#![no_main]
+
+extern crate libfuzzer_sys;
+extern crate serde;
+#[macro_use] extern crate serde_derive;
+extern crate tcp_channel;
+
+use libfuzzer_sys::fuzz_target;
+
+#[path = "../../tests/fuzz/harness.rs"]
+mod harness;
+
+fuzz_target!(|data: &[u8]| harness::recv_bytes(data));
@@ -0,0 +1,137 @@
+use std::io::{self, BufRead, Read, Write};
+
+use crate::{ChannelError, ChannelRecv, ChannelSend};
+
+/// The default number of bytes a `ChannelWriter` collects before sending them.
+pub const DEFAULT_CHUNK_SIZE: usize = 8192;
+
+/// Turns a channel of byte vectors into a writer.
+///
+/// Written bytes are collected until there are at least `chunk_size` of them, or until the writer
+/// is flushed, and then sent as one message. Dropping the writer flushes it, ignoring errors.
+///
+/// Like a `Sender`, a channel failing with `WouldBlock` or `TimedOut` is assumed to have queued
+/// the message, so those bytes are never sent twice: `write` still reports them as written, and
+/// the error surfaces from `flush`. Any other error is assumed to have dropped the message, as a
+/// `Sender` does before poisoning itself, so `write` keeps none of the bytes passed to it.
+pub struct ChannelWriter<C: ChannelSend<Vec<u8>>> where C::Error: Into<ChannelError> {
+    channel: C,
+    buffer: Vec<u8>,
+    chunk_size: usize,
+}
+impl<C: ChannelSend<Vec<u8>>> ChannelWriter<C> where C::Error: Into<ChannelError> {
+    /// Wrap a channel, using `DEFAULT_CHUNK_SIZE`.
+    pub fn new(channel: C) -> Self {
+        Self::with_chunk_size(channel, DEFAULT_CHUNK_SIZE)
+    }
+    /// Wrap a channel, sending written bytes once there are at least `chunk_size` of them.
+    pub fn with_chunk_size(channel: C, chunk_size: usize) -> Self {
+        Self {
+            channel,
+            buffer: Vec::new(),
+            chunk_size,
+        }
+    }
+    pub fn get_ref(&self) -> &C {
+        &self.channel
+    }
+    pub fn get_mut(&mut self) -> &mut C {
+        &mut self.channel
+    }
+    fn send_buffer(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let result = self.channel.send(&self.buffer).map_err(|error| io::Error::from(error.into()));
+        match result {
+            Err(ref error) if !is_queued(error.kind()) => (),
+            _ => self.buffer.clear(),
+        }
+        result
+    }
+}
+impl<C: ChannelSend<Vec<u8>>> Write for ChannelWriter<C> where C::Error: Into<ChannelError> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let length = self.buffer.len();
+        self.buffer.extend_from_slice(data);
+        if self.buffer.len() >= self.chunk_size {
+            match self.send_buffer() {
+                Err(ref error) if is_queued(error.kind()) => (),
+                Err(error) => {
+                    self.buffer.truncate(length);
+                    return Err(error);
+                },
+                Ok(()) => (),
+            }
+        }
+        Ok(data.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_buffer()?;
+        self.channel.flush().map_err(|error| error.into())?;
+        Ok(())
+    }
+}
+impl<C: ChannelSend<Vec<u8>>> Drop for ChannelWriter<C> where C::Error: Into<ChannelError> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+// Whether a failed send still queued the message, as the transient errors of a `Sender` do.
+fn is_queued(kind: io::ErrorKind) -> bool {
+    kind == io::ErrorKind::WouldBlock || kind == io::ErrorKind::TimedOut
+}
+
+/// Turns a channel of byte vectors into a reader.
+///
+/// The reader returns EOF once the channel is disconnected. Empty messages are skipped, rather
+/// than being mistaken for EOF.
+pub struct ChannelReader<C: ChannelRecv<Vec<u8>>> {
+    channel: C,
+    buffer: Vec<u8>,
+    position: usize,
+}
+impl<C: ChannelRecv<Vec<u8>>> ChannelReader<C> where C::Error: Into<ChannelError> {
+    /// Wrap a channel.
+    pub fn new(channel: C) -> Self {
+        Self {
+            channel,
+            buffer: Vec::new(),
+            position: 0,
+        }
+    }
+    pub fn get_ref(&self) -> &C {
+        &self.channel
+    }
+    pub fn get_mut(&mut self) -> &mut C {
+        &mut self.channel
+    }
+}
+impl<C: ChannelRecv<Vec<u8>>> BufRead for ChannelReader<C> where C::Error: Into<ChannelError> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.position == self.buffer.len() {
+            match self.channel.recv().map_err(Into::into) {
+                Ok(buffer) => {
+                    self.buffer = buffer;
+                    self.position = 0;
+                },
+                Err(ChannelError::Disconnected) => break,
+                Err(error) => return Err(error.into()),
+            }
+        }
+        Ok(&self.buffer[self.position..])
+    }
+    fn consume(&mut self, amount: usize) {
+        self.position = (self.position + amount).min(self.buffer.len());
+    }
+}
+impl<C: ChannelRecv<Vec<u8>>> Read for ChannelReader<C> where C::Error: Into<ChannelError> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let size = available.len().min(buffer.len());
+        buffer[..size].copy_from_slice(&available[..size]);
+        self.consume(size);
+        Ok(size)
+    }
+}
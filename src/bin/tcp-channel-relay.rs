@@ -0,0 +1,61 @@
+//! Accepts connections and relays the frames on each of them to a new connection to a target.
+//!
+//! Usage: `tcp-channel-relay <listen address> <target address> [max size]`
+
+extern crate tcp_channel;
+
+use std::env;
+use std::net::{TcpListener, TcpStream};
+use std::process;
+use std::thread;
+
+use tcp_channel::{Relay, DEFAULT_MAX_SIZE};
+
+fn main() {
+    let args = env::args().collect::<Vec<_>>();
+    if args.len() < 3 || args.len() > 4 {
+        eprintln!("usage: {} <listen address> <target address> [max size]", args[0]);
+        process::exit(2);
+    }
+    let max_size = match args.get(3).map(|max_size| max_size.parse()) {
+        None => DEFAULT_MAX_SIZE,
+        Some(Ok(max_size)) => max_size,
+        Some(Err(error)) => {
+            eprintln!("invalid max size: {}", error);
+            process::exit(2);
+        },
+    };
+
+    let listener = TcpListener::bind(&args[1]).unwrap_or_else(|error| {
+        eprintln!("failed to listen on {}: {}", args[1], error);
+        process::exit(1);
+    });
+
+    for client in listener.incoming() {
+        let client = match client {
+            Ok(client) => client,
+            Err(error) => {
+                eprintln!("failed to accept a connection: {}", error);
+                continue
+            },
+        };
+        let peer = client.peer_addr().map(|address| address.to_string()).unwrap_or_default();
+        let target = args[2].clone();
+
+        thread::spawn(move || {
+            let server = match TcpStream::connect(&target) {
+                Ok(server) => server,
+                Err(error) => return eprintln!("{}: failed to connect to {}: {}", peer, target, error),
+            };
+            eprintln!("{}: relaying to {}", peer, target);
+
+            match Relay::new().with_max_size(max_size).run(client, server) {
+                Ok(stats) => eprintln!(
+                    "{}: closed, {} frames ({} bytes) sent, {} frames ({} bytes) received",
+                    peer, stats.a_to_b.frames, stats.a_to_b.bytes, stats.b_to_a.frames, stats.b_to_a.bytes,
+                ),
+                Err(error) => eprintln!("{}: closed: {}", peer, error),
+            }
+        });
+    }
+}
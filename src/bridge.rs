@@ -0,0 +1,116 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+
+use crate::{ChannelRecv, ChannelSend};
+
+/// The error that stopped a forwarding thread.
+#[derive(Debug)]
+pub enum ForwardError<R, S> {
+    /// Receiving from the source failed.
+    Recv(R),
+    /// Sending to the destination failed.
+    Send(S),
+}
+impl<R: fmt::Display, S: fmt::Display> fmt::Display for ForwardError<R, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ForwardError::Recv(err) => write!(f, "failed to receive: {}", err),
+            ForwardError::Send(err) => write!(f, "failed to send: {}", err),
+        }
+    }
+}
+impl<R: Error, S: Error> Error for ForwardError<R, S> {}
+
+/// A handle to a thread spawned by `forward`.
+pub struct ForwardHandle<R, S> {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<Result<(), ForwardError<R, S>>>,
+}
+impl<R, S> ForwardHandle<R, S> {
+    /// Ask the thread to stop.
+    ///
+    /// The flag is checked between messages, so a thread blocked in `recv` stops only once that
+    /// call returns.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+    /// Check whether the thread has exited.
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+    /// Wait for the thread to exit, returning the error that terminated it, if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the forwarding thread panicked.
+    pub fn join(self) -> Result<(), ForwardError<R, S>> {
+        self.thread.join().expect("forwarding thread panicked")
+    }
+    /// Stop the thread and wait for it to exit.
+    pub fn stop_and_join(self) -> Result<(), ForwardError<R, S>> {
+        self.stop();
+        self.join()
+    }
+}
+
+/// Spawn a thread that receives every message from `receiver` and sends it to `sender`.
+///
+/// The sender is flushed after every message. The thread runs until it is stopped, until the
+/// receiver reports that the other side has disconnected, or until either side returns any other
+/// error, which is then returned by `ForwardHandle::join`.
+pub fn forward<T, R, S>(mut receiver: R, mut sender: S) -> ForwardHandle<R::Error, S::Error>
+where
+    T: 'static,
+    R: ChannelRecv<T> + Send + 'static,
+    S: ChannelSend<T> + Send + 'static,
+    R::Error: Send + 'static,
+    S::Error: Send + 'static,
+{
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_flag = Arc::clone(&stop);
+
+    let thread = thread::spawn(move || {
+        while !stop_flag.load(Ordering::SeqCst) {
+            let value = match receiver.recv() {
+                Ok(value) => value,
+                Err(ref error) if receiver.is_disconnected(error) => break,
+                Err(error) => return Err(ForwardError::Recv(error)),
+            };
+            sender.send(&value).map_err(ForwardError::Send)?;
+            sender.flush().map_err(ForwardError::Send)?;
+        }
+        Ok(())
+    });
+
+    ForwardHandle {
+        stop,
+        thread,
+    }
+}
+
+/// Connect two channel endpoints in both directions, forwarding everything received from `a` to
+/// `b`, and everything received from `b` to `a`.
+///
+/// The first handle forwards from `a` to `b`, the second one from `b` to `a`.
+#[allow(clippy::type_complexity)]
+pub fn bridge<T, U, RA, SA, RB, SB>(a: (RA, SA), b: (RB, SB)) -> (ForwardHandle<RA::Error, SB::Error>, ForwardHandle<RB::Error, SA::Error>)
+where
+    T: 'static,
+    U: 'static,
+    RA: ChannelRecv<T> + Send + 'static,
+    SB: ChannelSend<T> + Send + 'static,
+    RB: ChannelRecv<U> + Send + 'static,
+    SA: ChannelSend<U> + Send + 'static,
+    RA::Error: Send + 'static,
+    SA::Error: Send + 'static,
+    RB::Error: Send + 'static,
+    SB::Error: Send + 'static,
+{
+    let (receiver_a, sender_a) = a;
+    let (receiver_b, sender_b) = b;
+
+    (forward(receiver_a, sender_b), forward(receiver_b, sender_a))
+}
@@ -1,12 +1,23 @@
-use std::sync::mpsc::{Sender as StdSender, Receiver as StdReceiver, SendError as StdSendError, RecvError as StdRecvError};
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::mpsc::{Sender as StdSender, SyncSender as StdSyncSender, Receiver as StdReceiver, SendError as StdSendError, RecvError as StdRecvError};
 
 pub trait ChannelSend<T> {
     type Error;
     fn send(&mut self, value: &T) -> Result<(), Self::Error>;
+    /// Flush any buffered messages. Channels that don't buffer don't need to override this.
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
 pub trait ChannelRecv<T> {
     type Error;
     fn recv(&mut self) -> Result<T, Self::Error>;
+    /// Whether an error returned by `recv` means that the other side has disconnected, which
+    /// ends the channel normally. Channels that can't tell don't need to override this.
+    fn is_disconnected(&self, _error: &Self::Error) -> bool {
+        false
+    }
 }
 
 impl<T: Clone> ChannelSend<T> for StdSender<T> {
@@ -16,10 +27,102 @@ impl<T: Clone> ChannelSend<T> for StdSender<T> {
         StdSender::send(self, value.clone())
     }
 }
+impl<T: Clone> ChannelSend<T> for StdSyncSender<T> {
+    type Error = StdSendError<T>;
+
+    fn send(&mut self, value: &T) -> Result<(), Self::Error> {
+        StdSyncSender::send(self, value.clone())
+    }
+}
 impl<T> ChannelRecv<T> for StdReceiver<T> {
     type Error = StdRecvError;
 
     fn recv(&mut self) -> Result<T, Self::Error> {
         StdReceiver::recv(self)
     }
+    fn is_disconnected(&self, _error: &Self::Error) -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "crossbeam-channel")]
+impl<T: Clone> ChannelSend<T> for crossbeam_channel::Sender<T> {
+    type Error = crossbeam_channel::SendError<T>;
+
+    fn send(&mut self, value: &T) -> Result<(), Self::Error> {
+        crossbeam_channel::Sender::send(self, value.clone())
+    }
+}
+#[cfg(feature = "crossbeam-channel")]
+impl<T> ChannelRecv<T> for crossbeam_channel::Receiver<T> {
+    type Error = crossbeam_channel::RecvError;
+
+    fn recv(&mut self) -> Result<T, Self::Error> {
+        crossbeam_channel::Receiver::recv(self)
+    }
+    fn is_disconnected(&self, _error: &Self::Error) -> bool {
+        true
+    }
+}
+
+// A queue acts as a channel whose sender and receiver are the same value, which is mostly useful
+// for tests. Receiving from an empty queue fails like receiving from a disconnected channel.
+impl<T: Clone> ChannelSend<T> for VecDeque<T> {
+    type Error = Infallible;
+
+    fn send(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.push_back(value.clone());
+        Ok(())
+    }
+}
+impl<T> ChannelRecv<T> for VecDeque<T> {
+    type Error = StdRecvError;
+
+    fn recv(&mut self) -> Result<T, Self::Error> {
+        self.pop_front().ok_or(StdRecvError)
+    }
+    fn is_disconnected(&self, _error: &Self::Error) -> bool {
+        true
+    }
+}
+
+impl<T, C: ChannelSend<T> + ?Sized> ChannelSend<T> for &mut C {
+    type Error = C::Error;
+
+    fn send(&mut self, value: &T) -> Result<(), Self::Error> {
+        (**self).send(value)
+    }
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        (**self).flush()
+    }
+}
+impl<T, C: ChannelRecv<T> + ?Sized> ChannelRecv<T> for &mut C {
+    type Error = C::Error;
+
+    fn recv(&mut self) -> Result<T, Self::Error> {
+        (**self).recv()
+    }
+    fn is_disconnected(&self, error: &Self::Error) -> bool {
+        (**self).is_disconnected(error)
+    }
+}
+impl<T, C: ChannelSend<T> + ?Sized> ChannelSend<T> for Box<C> {
+    type Error = C::Error;
+
+    fn send(&mut self, value: &T) -> Result<(), Self::Error> {
+        (**self).send(value)
+    }
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        (**self).flush()
+    }
+}
+impl<T, C: ChannelRecv<T> + ?Sized> ChannelRecv<T> for Box<C> {
+    type Error = C::Error;
+
+    fn recv(&mut self) -> Result<T, Self::Error> {
+        (**self).recv()
+    }
+    fn is_disconnected(&self, error: &Self::Error) -> bool {
+        (**self).is_disconnected(error)
+    }
 }
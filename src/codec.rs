@@ -0,0 +1,176 @@
+use bincode::{DefaultOptions, Options};
+use serde::{Deserialize, Serialize};
+
+use crate::ByteOrderChoice;
+
+/// How bincode encodes integers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IntEncoding {
+    /// Every integer takes its full size. This is the default.
+    #[default]
+    Fixint,
+    /// Small integers take fewer bytes.
+    Varint,
+}
+
+/// What bincode does with bytes left over after deserializing a message.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrailingBytes {
+    /// Ignore them. This is the default.
+    #[default]
+    Allow,
+    /// Fail with an error.
+    Reject,
+}
+
+/// The bincode configuration used to serialize and deserialize messages.
+///
+/// The default matches the encoding used by previous versions: fixed-size integers, trailing
+/// bytes allowed, and no limit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Codec {
+    byte_order: ByteOrderChoice,
+    int_encoding: IntEncoding,
+    trailing_bytes: TrailingBytes,
+    limit: Option<u64>,
+}
+impl Codec {
+    pub fn new(byte_order: ByteOrderChoice) -> Self {
+        Self {
+            byte_order,
+            ..Self::default()
+        }
+    }
+    pub fn with_byte_order(mut self, byte_order: ByteOrderChoice) -> Self {
+        self.byte_order = byte_order;
+        self
+    }
+    pub fn with_int_encoding(mut self, int_encoding: IntEncoding) -> Self {
+        self.int_encoding = int_encoding;
+        self
+    }
+    pub fn with_trailing_bytes(mut self, trailing_bytes: TrailingBytes) -> Self {
+        self.trailing_bytes = trailing_bytes;
+        self
+    }
+    /// Limit the number of bytes bincode reads or writes, which also stops it from allocating
+    /// for lengths inside a message that exceed the limit.
+    pub fn with_limit(mut self, limit: Option<u64>) -> Self {
+        self.limit = limit;
+        self
+    }
+    pub fn byte_order(&self) -> ByteOrderChoice {
+        self.byte_order
+    }
+    pub fn int_encoding(&self) -> IntEncoding {
+        self.int_encoding
+    }
+    pub fn trailing_bytes(&self) -> TrailingBytes {
+        self.trailing_bytes
+    }
+    pub fn limit(&self) -> Option<u64> {
+        self.limit
+    }
+
+    pub fn serialize<T: Serialize + ?Sized>(&self, value: &T) -> bincode::Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        self.serialize_into(&mut buffer, value)?;
+        Ok(buffer)
+    }
+    /// Serialize a value, appending it to a buffer.
+    pub fn serialize_into<T: Serialize + ?Sized>(&self, buffer: &mut Vec<u8>, value: &T) -> bincode::Result<()> {
+        self.call(SerializeInto {
+            buffer,
+            value,
+        })
+    }
+    /// The number of bytes `serialize` would produce, computed without allocating.
+    pub fn serialized_size<T: Serialize + ?Sized>(&self, value: &T) -> bincode::Result<u64> {
+        self.call(SerializedSize {
+            value,
+        })
+    }
+    pub fn deserialize<'a, T: Deserialize<'a>>(&self, bytes: &'a [u8]) -> bincode::Result<T> {
+        self.call(DeserializeFrom {
+            bytes,
+            _marker: std::marker::PhantomData,
+        })
+    }
+
+    // bincode's options are chosen using types, so every combination has to be spelled out.
+    fn call<O: Operation>(&self, operation: O) -> O::Output {
+        let options = DefaultOptions::new().with_limit(self.limit.unwrap_or(u64::MAX));
+        match self.byte_order {
+            ByteOrderChoice::BigEndian => self.call_with_endian(options.with_big_endian(), operation),
+            ByteOrderChoice::LittleEndian => self.call_with_endian(options.with_little_endian(), operation),
+        }
+    }
+    fn call_with_endian<B: Options, O: Operation>(&self, options: B, operation: O) -> O::Output {
+        match self.int_encoding {
+            IntEncoding::Fixint => self.call_with_int_encoding(options.with_fixint_encoding(), operation),
+            IntEncoding::Varint => self.call_with_int_encoding(options.with_varint_encoding(), operation),
+        }
+    }
+    fn call_with_int_encoding<B: Options, O: Operation>(&self, options: B, operation: O) -> O::Output {
+        match self.trailing_bytes {
+            TrailingBytes::Allow => operation.call(options.allow_trailing_bytes()),
+            TrailingBytes::Reject => operation.call(options.reject_trailing_bytes()),
+        }
+    }
+}
+
+trait Operation {
+    type Output;
+    fn call<B: Options>(self, options: B) -> Self::Output;
+}
+
+struct SerializeInto<'a, T: ?Sized> {
+    buffer: &'a mut Vec<u8>,
+    value: &'a T,
+}
+impl<'a, T: Serialize + ?Sized> Operation for SerializeInto<'a, T> {
+    type Output = bincode::Result<()>;
+
+    fn call<B: Options>(self, options: B) -> bincode::Result<()> {
+        options.serialize_into(self.buffer, self.value)
+    }
+}
+
+struct SerializedSize<'a, T: ?Sized> {
+    value: &'a T,
+}
+impl<'a, T: Serialize + ?Sized> Operation for SerializedSize<'a, T> {
+    type Output = bincode::Result<u64>;
+
+    fn call<B: Options>(self, options: B) -> bincode::Result<u64> {
+        options.serialized_size(self.value)
+    }
+}
+
+struct DeserializeFrom<'a, T> {
+    bytes: &'a [u8],
+    _marker: std::marker::PhantomData<T>,
+}
+impl<'a, T: Deserialize<'a>> Operation for DeserializeFrom<'a, T> {
+    type Output = bincode::Result<T>;
+
+    fn call<B: Options>(self, options: B) -> bincode::Result<T> {
+        options.deserialize(self.bytes)
+    }
+}
+
+impl IntEncoding {
+    pub(crate) fn to_byte(self) -> u8 {
+        match self {
+            IntEncoding::Fixint => 0,
+            IntEncoding::Varint => 1,
+        }
+    }
+    pub(crate) fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(IntEncoding::Fixint),
+            1 => Some(IntEncoding::Varint),
+            _ => None,
+        }
+    }
+}
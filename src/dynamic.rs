@@ -0,0 +1,79 @@
+use crate::{ChannelError, ChannelRecv, ChannelSend};
+
+// Converts the errors of the wrapped channel into `ChannelError`.
+pub(crate) struct ErrorInto<C>(pub(crate) C);
+
+impl<T, C: ChannelSend<T>> ChannelSend<T> for ErrorInto<C> where C::Error: Into<ChannelError> {
+    type Error = ChannelError;
+
+    fn send(&mut self, value: &T) -> Result<(), ChannelError> {
+        self.0.send(value).map_err(Into::into)
+    }
+    fn flush(&mut self) -> Result<(), ChannelError> {
+        self.0.flush().map_err(Into::into)
+    }
+}
+impl<T, C: ChannelRecv<T>> ChannelRecv<T> for ErrorInto<C> where C::Error: Into<ChannelError> {
+    type Error = ChannelError;
+
+    fn recv(&mut self) -> Result<T, ChannelError> {
+        self.0.recv().map_err(Into::into)
+    }
+    fn is_disconnected(&self, error: &ChannelError) -> bool {
+        matches!(error, ChannelError::Disconnected)
+    }
+}
+
+/// A sending side of any channel, with both the transport and the error type erased.
+pub struct DynSender<T> {
+    inner: Box<dyn ChannelSend<T, Error = ChannelError> + Send>,
+}
+impl<T> DynSender<T> {
+    /// Wrap a channel.
+    pub fn new<C>(channel: C) -> Self
+    where
+        C: ChannelSend<T> + Send + 'static,
+        C::Error: Into<ChannelError>,
+    {
+        Self {
+            inner: Box::new(ErrorInto(channel)),
+        }
+    }
+}
+impl<T> ChannelSend<T> for DynSender<T> {
+    type Error = ChannelError;
+
+    fn send(&mut self, value: &T) -> Result<(), ChannelError> {
+        self.inner.send(value)
+    }
+    fn flush(&mut self) -> Result<(), ChannelError> {
+        self.inner.flush()
+    }
+}
+
+/// A receiving side of any channel, with both the transport and the error type erased.
+pub struct DynReceiver<T> {
+    inner: Box<dyn ChannelRecv<T, Error = ChannelError> + Send>,
+}
+impl<T> DynReceiver<T> {
+    /// Wrap a channel.
+    pub fn new<C>(channel: C) -> Self
+    where
+        C: ChannelRecv<T> + Send + 'static,
+        C::Error: Into<ChannelError>,
+    {
+        Self {
+            inner: Box::new(ErrorInto(channel)),
+        }
+    }
+}
+impl<T> ChannelRecv<T> for DynReceiver<T> {
+    type Error = ChannelError;
+
+    fn recv(&mut self) -> Result<T, ChannelError> {
+        self.inner.recv()
+    }
+    fn is_disconnected(&self, error: &ChannelError) -> bool {
+        self.inner.is_disconnected(error)
+    }
+}
@@ -1,21 +1,93 @@
+use std::fmt;
+use std::str::FromStr;
+
 pub use byteorder::{BigEndian, LittleEndian, NativeEndian};
 use byteorder::ByteOrder;
-use bincode::Config;
 
+use crate::Codec;
+
+/// An endianness that can be chosen at compile time, using `with_endianness` on the builders.
+///
+/// `NativeEndian` is an alias for whichever of `BigEndian` and `LittleEndian` the target uses,
+/// so it can be used as well.
 pub trait Endian: ByteOrder {
-    fn config() -> Config;
+    /// The default codec, using this endianness.
+    fn config() -> Codec;
+    /// The same endianness, as a runtime value.
+    fn byte_order() -> ByteOrderChoice;
 }
 impl Endian for BigEndian {
-    fn config() -> Config {
-        let mut config = bincode::config();
-        config.big_endian();
-        config
+    fn config() -> Codec {
+        Codec::new(ByteOrderChoice::BigEndian)
+    }
+    fn byte_order() -> ByteOrderChoice {
+        ByteOrderChoice::BigEndian
     }
 }
 impl Endian for LittleEndian {
-    fn config() -> Config {
-        let mut config = bincode::config();
-        config.little_endian();
-        config
+    fn config() -> Codec {
+        Codec::new(ByteOrderChoice::LittleEndian)
+    }
+    fn byte_order() -> ByteOrderChoice {
+        ByteOrderChoice::LittleEndian
+    }
+}
+
+/// An endianness chosen at runtime, for example from configuration, using `with_byte_order` on
+/// the builders. It overrides the endianness type parameter.
+///
+/// Parsing accepts `big`, `little` and `native`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ByteOrderChoice {
+    /// The default, like `BigEndian` is for the builders.
+    #[default]
+    BigEndian,
+    LittleEndian,
+}
+impl ByteOrderChoice {
+    /// The endianness of the target.
+    pub fn native() -> Self {
+        if cfg!(target_endian = "big") {
+            ByteOrderChoice::BigEndian
+        } else {
+            ByteOrderChoice::LittleEndian
+        }
+    }
+    pub(crate) fn to_byte(self) -> u8 {
+        match self {
+            ByteOrderChoice::BigEndian => 0,
+            ByteOrderChoice::LittleEndian => 1,
+        }
+    }
+    pub(crate) fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(ByteOrderChoice::BigEndian),
+            1 => Some(ByteOrderChoice::LittleEndian),
+            _ => None,
+        }
+    }
+}
+
+/// The error returned when parsing a `ByteOrderChoice` fails.
+#[derive(Debug)]
+pub struct ParseByteOrderError;
+
+impl fmt::Display for ParseByteOrderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "expected `big`, `little` or `native`")
+    }
+}
+impl std::error::Error for ParseByteOrderError {}
+
+impl FromStr for ByteOrderChoice {
+    type Err = ParseByteOrderError;
+
+    fn from_str(string: &str) -> Result<Self, ParseByteOrderError> {
+        match string {
+            "big" => Ok(ByteOrderChoice::BigEndian),
+            "little" => Ok(ByteOrderChoice::LittleEndian),
+            "native" => Ok(ByteOrderChoice::native()),
+            _ => Err(ParseByteOrderError),
+        }
     }
 }
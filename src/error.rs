@@ -1,11 +1,37 @@
+use std::convert::Infallible;
+use std::error::Error;
+use std::sync::mpsc::{SendError as StdSendError, RecvError as StdRecvError};
+
 use bincode::Error as BincodeError;
-use std::io::Error as IoError;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
 
 use quick_error::quick_error;
 
 quick_error! {
     #[derive(Debug)]
     pub enum RecvError {
+        Disconnected {}
+        /// A frame failed to deserialize. The frame has been consumed, so receiving can continue
+        /// with the next one, and its bytes are included for logging.
+        BincodeError(err: BincodeError, frame: Vec<u8>) {}
+        IoError(err: IoError) {
+            from()
+        }
+        TooLarge(size: usize) {}
+        InvalidHeader {}
+        InvalidHandshake {}
+        MaxSizeMismatch(sender: usize, receiver: usize) {}
+        /// The fingerprint of the sender's message type, if it announced one, differs from the
+        /// receiver's.
+        SchemaMismatch(sender: Option<u64>, receiver: u64) {}
+        /// An earlier error left the stream in an unknown position, so nothing more can be
+        /// received.
+        Poisoned {}
+    }
+}
+quick_error! {
+    #[derive(Debug)]
+    pub enum SendError {
         Disconnected {}
         BincodeError(err: BincodeError) {
             from()
@@ -14,11 +40,16 @@ quick_error! {
             from()
         }
         TooLarge(size: usize) {}
+        /// An earlier write failed partway through, so nothing more can be sent.
+        Poisoned {}
+        /// A stream was sent with a max size of 0, which leaves no room for its chunks.
+        ZeroMaxSize {}
     }
 }
 quick_error! {
+    /// An error from any kind of channel, used where the transport is erased.
     #[derive(Debug)]
-    pub enum SendError {
+    pub enum ChannelError {
         Disconnected {}
         BincodeError(err: BincodeError) {
             from()
@@ -26,5 +57,76 @@ quick_error! {
         IoError(err: IoError) {
             from()
         }
+        TooLarge(size: usize) {}
+        Other(err: Box<dyn Error + Send + Sync>) {}
+    }
+}
+
+impl From<RecvError> for ChannelError {
+    fn from(error: RecvError) -> Self {
+        match error {
+            RecvError::Disconnected => ChannelError::Disconnected,
+            RecvError::BincodeError(err, _) => ChannelError::BincodeError(err),
+            RecvError::IoError(err) => ChannelError::IoError(err),
+            RecvError::TooLarge(size) => ChannelError::TooLarge(size),
+            error => ChannelError::Other(Box::new(error)),
+        }
+    }
+}
+impl From<SendError> for ChannelError {
+    fn from(error: SendError) -> Self {
+        match error {
+            SendError::Disconnected => ChannelError::Disconnected,
+            SendError::BincodeError(err) => ChannelError::BincodeError(err),
+            SendError::IoError(err) => ChannelError::IoError(err),
+            SendError::TooLarge(size) => ChannelError::TooLarge(size),
+            error => ChannelError::Other(Box::new(error)),
+        }
+    }
+}
+impl From<RecvError> for IoError {
+    fn from(error: RecvError) -> Self {
+        match error {
+            RecvError::IoError(err) => err,
+            RecvError::Disconnected => IoErrorKind::UnexpectedEof.into(),
+            error => IoError::new(IoErrorKind::InvalidData, error),
+        }
+    }
+}
+impl From<ChannelError> for IoError {
+    fn from(error: ChannelError) -> Self {
+        match error {
+            ChannelError::IoError(err) => err,
+            ChannelError::Disconnected => IoErrorKind::BrokenPipe.into(),
+            ChannelError::Other(err) => IoError::other(err),
+            error => IoError::new(IoErrorKind::InvalidData, error),
+        }
+    }
+}
+impl<T> From<StdSendError<T>> for ChannelError {
+    fn from(_: StdSendError<T>) -> Self {
+        ChannelError::Disconnected
+    }
+}
+impl From<StdRecvError> for ChannelError {
+    fn from(_: StdRecvError) -> Self {
+        ChannelError::Disconnected
+    }
+}
+#[cfg(feature = "crossbeam-channel")]
+impl<T> From<crossbeam_channel::SendError<T>> for ChannelError {
+    fn from(_: crossbeam_channel::SendError<T>) -> Self {
+        ChannelError::Disconnected
+    }
+}
+#[cfg(feature = "crossbeam-channel")]
+impl From<crossbeam_channel::RecvError> for ChannelError {
+    fn from(_: crossbeam_channel::RecvError) -> Self {
+        ChannelError::Disconnected
+    }
+}
+impl From<Infallible> for ChannelError {
+    fn from(error: Infallible) -> Self {
+        match error {}
     }
 }
@@ -0,0 +1,138 @@
+// The optional handshake is a preamble that the sender writes before its first frame, and that
+// the receiver reads before its first frame. It is always big endian, regardless of the endianness
+// of the channel, and consists of:
+//
+// - the magic bytes `TCPC`,
+// - a version byte,
+// - the length of the remainder as a `u16`,
+// - a sequence of fields, each being a tag byte, a length byte, and the value.
+//
+// Unknown fields are skipped, so that fields can be added without changing the version.
+
+use std::convert::TryInto;
+
+use crate::{ByteOrderChoice, HeaderEncoding, IntEncoding, RecvError};
+
+const MAGIC: &[u8; 4] = b"TCPC";
+const VERSION: u8 = 1;
+
+/// The length of the magic bytes, version and body length.
+pub(crate) const PREFIX_LENGTH: usize = 7;
+
+const TAG_MAX_SIZE: u8 = 1;
+const TAG_HEADER: u8 = 2;
+const TAG_BYTE_ORDER: u8 = 3;
+const TAG_INT_ENCODING: u8 = 4;
+const TAG_FINGERPRINT: u8 = 5;
+
+/// The parameters announced by a sender.
+#[derive(Debug, Default)]
+pub(crate) struct Handshake {
+    pub(crate) max_size: Option<u64>,
+    pub(crate) header: Option<HeaderEncoding>,
+    pub(crate) byte_order: Option<ByteOrderChoice>,
+    pub(crate) int_encoding: Option<IntEncoding>,
+    pub(crate) fingerprint: Option<u64>,
+}
+impl Handshake {
+    /// Append the encoded handshake to a buffer.
+    pub(crate) fn encode(&self, buffer: &mut Vec<u8>) {
+        let start = buffer.len();
+        buffer.extend_from_slice(MAGIC);
+        buffer.push(VERSION);
+        buffer.extend_from_slice(&[0; 2]);
+
+        if let Some(max_size) = self.max_size {
+            push_field(buffer, TAG_MAX_SIZE, &max_size.to_be_bytes());
+        }
+        if let Some(header) = self.header {
+            push_field(buffer, TAG_HEADER, &[header.to_byte()]);
+        }
+        if let Some(byte_order) = self.byte_order {
+            push_field(buffer, TAG_BYTE_ORDER, &[byte_order.to_byte()]);
+        }
+        if let Some(int_encoding) = self.int_encoding {
+            push_field(buffer, TAG_INT_ENCODING, &[int_encoding.to_byte()]);
+        }
+        if let Some(fingerprint) = self.fingerprint {
+            push_field(buffer, TAG_FINGERPRINT, &fingerprint.to_be_bytes());
+        }
+
+        let length = (buffer.len() - start - PREFIX_LENGTH) as u16;
+        buffer[start + 5..start + PREFIX_LENGTH].copy_from_slice(&length.to_be_bytes());
+    }
+    /// Check the magic bytes and version, returning the length of the body.
+    pub(crate) fn decode_prefix(prefix: &[u8]) -> Result<usize, RecvError> {
+        if &prefix[..4] != MAGIC || prefix[4] != VERSION {
+            return Err(RecvError::InvalidHandshake)
+        }
+        Ok(u16::from_be_bytes([prefix[5], prefix[6]]) as usize)
+    }
+    pub(crate) fn decode_body(mut body: &[u8]) -> Result<Self, RecvError> {
+        let mut handshake = Self::default();
+
+        while !body.is_empty() {
+            if body.len() < 2 || body.len() < 2 + body[1] as usize {
+                return Err(RecvError::InvalidHandshake)
+            }
+            let (tag, value) = (body[0], &body[2..2 + body[1] as usize]);
+            body = &body[2 + value.len()..];
+
+            match tag {
+                TAG_MAX_SIZE => handshake.max_size = Some(decode_u64(value)?),
+                TAG_HEADER => handshake.header = Some(decode_byte(value, HeaderEncoding::from_byte)?),
+                TAG_BYTE_ORDER => handshake.byte_order = Some(decode_byte(value, ByteOrderChoice::from_byte)?),
+                TAG_INT_ENCODING => handshake.int_encoding = Some(decode_byte(value, IntEncoding::from_byte)?),
+                TAG_FINGERPRINT => handshake.fingerprint = Some(decode_u64(value)?),
+                _ => (),
+            }
+        }
+
+        Ok(handshake)
+    }
+}
+
+/// How the fingerprint of the message type is determined.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Schema {
+    Version(u64),
+    Fingerprint(u64),
+}
+impl Schema {
+    pub(crate) fn fingerprint<T: ?Sized>(self) -> u64 {
+        match self {
+            Schema::Version(version) => schema_fingerprint::<T>(version),
+            Schema::Fingerprint(fingerprint) => fingerprint,
+        }
+    }
+}
+
+/// The fingerprint of a message type, as used by `with_schema_version` on the builders: a 64-bit
+/// FNV-1a hash of the type name and the version.
+///
+/// The type name includes the module path, so both sides have to use the type from the same
+/// crate. It isn't guaranteed to be stable across compiler versions either, so peers that are
+/// built separately should use `with_fingerprint` with a hash of their own instead.
+pub fn schema_fingerprint<T: ?Sized>(version: u64) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for byte in std::any::type_name::<T>().bytes().chain(version.to_be_bytes().iter().cloned()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+fn decode_byte<T>(value: &[u8], from_byte: fn(u8) -> Option<T>) -> Result<T, RecvError> {
+    match value {
+        [byte] => from_byte(*byte).ok_or(RecvError::InvalidHandshake),
+        _ => Err(RecvError::InvalidHandshake),
+    }
+}
+fn decode_u64(value: &[u8]) -> Result<u64, RecvError> {
+    Ok(u64::from_be_bytes(value.try_into().map_err(|_| RecvError::InvalidHandshake)?))
+}
+fn push_field(buffer: &mut Vec<u8>, tag: u8, value: &[u8]) {
+    buffer.push(tag);
+    buffer.push(value.len() as u8);
+    buffer.extend_from_slice(value);
+}
@@ -0,0 +1,137 @@
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+
+use crate::ByteOrderChoice;
+
+/// The most bytes any header encoding uses.
+pub(crate) const MAX_HEADER_LENGTH: usize = 10;
+
+/// How the length of each frame is encoded in front of it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HeaderEncoding {
+    /// A `u16` in the endianness of the channel, limiting messages to 64 KiB.
+    U16,
+    /// A `u32` in the endianness of the channel.
+    U32,
+    /// A `u64` in the endianness of the channel. This is the default.
+    #[default]
+    U64,
+    /// An unsigned LEB128 varint, which takes a single byte for messages below 128 bytes.
+    Varint,
+}
+impl HeaderEncoding {
+    /// The largest length that can be encoded.
+    pub fn max_length(self) -> u64 {
+        match self {
+            HeaderEncoding::U16 => u16::MAX.into(),
+            HeaderEncoding::U32 => u32::MAX.into(),
+            HeaderEncoding::U64 | HeaderEncoding::Varint => u64::MAX,
+        }
+    }
+    /// The most bytes a header can take.
+    pub(crate) fn reserved_length(self) -> usize {
+        match self {
+            HeaderEncoding::U16 => 2,
+            HeaderEncoding::U32 => 4,
+            HeaderEncoding::U64 => 8,
+            HeaderEncoding::Varint => MAX_HEADER_LENGTH,
+        }
+    }
+    /// The number of bytes the header of a frame with this payload length takes.
+    pub(crate) fn encoded_length(self, length: u64) -> usize {
+        match self {
+            HeaderEncoding::Varint => {
+                let mut size = 1;
+                let mut length = length >> 7;
+                while length != 0 {
+                    size += 1;
+                    length >>= 7;
+                }
+                size
+            },
+            header => header.reserved_length(),
+        }
+    }
+    /// Encode a length, which must not be above `max_length`, returning the number of bytes used.
+    pub(crate) fn encode(self, byte_order: ByteOrderChoice, length: u64, header: &mut [u8; MAX_HEADER_LENGTH]) -> usize {
+        match byte_order {
+            ByteOrderChoice::BigEndian => self.encode_as::<BigEndian>(length, header),
+            ByteOrderChoice::LittleEndian => self.encode_as::<LittleEndian>(length, header),
+        }
+    }
+    fn encode_as<E: ByteOrder>(self, length: u64, header: &mut [u8; MAX_HEADER_LENGTH]) -> usize {
+        match self {
+            HeaderEncoding::U16 => E::write_u16(header, length as u16),
+            HeaderEncoding::U32 => E::write_u32(header, length as u32),
+            HeaderEncoding::U64 => E::write_u64(header, length),
+            HeaderEncoding::Varint => {
+                let mut length = length;
+                let mut size = 0;
+                loop {
+                    let byte = (length & 0x7F) as u8;
+                    length >>= 7;
+                    if length == 0 {
+                        header[size] = byte;
+                        return size + 1
+                    }
+                    header[size] = byte | 0x80;
+                    size += 1;
+                }
+            },
+        }
+        self.reserved_length()
+    }
+    /// The number of bytes that can be read next without reading past the end of the header,
+    /// given the bytes read so far. Returns zero once the header is complete.
+    pub(crate) fn remaining(self, header: &[u8]) -> usize {
+        match self {
+            HeaderEncoding::Varint => match header.last() {
+                Some(byte) if byte & 0x80 == 0 => 0,
+                _ => 1,
+            },
+            _ => self.reserved_length() - header.len(),
+        }
+    }
+    /// Decode a complete header, returning `None` if it is invalid.
+    pub(crate) fn decode(self, byte_order: ByteOrderChoice, header: &[u8]) -> Option<u64> {
+        match byte_order {
+            ByteOrderChoice::BigEndian => self.decode_as::<BigEndian>(header),
+            ByteOrderChoice::LittleEndian => self.decode_as::<LittleEndian>(header),
+        }
+    }
+    fn decode_as<E: ByteOrder>(self, header: &[u8]) -> Option<u64> {
+        match self {
+            HeaderEncoding::U16 => Some(E::read_u16(header).into()),
+            HeaderEncoding::U32 => Some(E::read_u32(header).into()),
+            HeaderEncoding::U64 => Some(E::read_u64(header)),
+            HeaderEncoding::Varint => {
+                let mut length = 0u64;
+                for (index, byte) in header.iter().enumerate() {
+                    let bits = u64::from(byte & 0x7F);
+                    // The tenth byte may only contain the single remaining bit.
+                    if index == MAX_HEADER_LENGTH - 1 && bits > 1 {
+                        return None
+                    }
+                    length |= bits << (7 * index);
+                }
+                Some(length)
+            },
+        }
+    }
+    pub(crate) fn to_byte(self) -> u8 {
+        match self {
+            HeaderEncoding::U64 => 0,
+            HeaderEncoding::U32 => 1,
+            HeaderEncoding::U16 => 2,
+            HeaderEncoding::Varint => 3,
+        }
+    }
+    pub(crate) fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(HeaderEncoding::U64),
+            1 => Some(HeaderEncoding::U32),
+            2 => Some(HeaderEncoding::U16),
+            3 => Some(HeaderEncoding::Varint),
+            _ => None,
+        }
+    }
+}
@@ -3,17 +3,59 @@
 
 extern crate bincode;
 extern crate byteorder;
+#[cfg(feature = "crossbeam-channel")]
+extern crate crossbeam_channel;
+#[cfg(feature = "metrics")]
+extern crate metrics;
+#[cfg(feature = "tracing")]
+extern crate tracing;
 extern crate quick_error;
 extern crate serde;
+#[cfg(feature = "derive")]
+extern crate tcp_channel_derive;
 
+#[macro_use]
+mod trace;
+
+mod adapter;
+mod bridge;
 mod channel;
+mod codec;
+mod dynamic;
 mod endian;
 mod error;
+mod handshake;
+mod header;
+mod protocol;
 mod receiver;
+mod relay;
+mod router;
 mod sender;
+mod stats;
+
+pub mod testing;
 
+pub use adapter::{ChannelReader, ChannelWriter, DEFAULT_CHUNK_SIZE};
+pub use bridge::{bridge, forward, ForwardError, ForwardHandle};
 pub use channel::{ChannelRecv, ChannelSend};
-pub use endian::{Endian, BigEndian, LittleEndian, NativeEndian};
-pub use error::{RecvError, SendError};
-pub use receiver::{Receiver, ReceiverBuilder, DEFAULT_MAX_SIZE};
+pub use codec::{Codec, IntEncoding, TrailingBytes};
+pub use dynamic::{DynReceiver, DynSender};
+pub use endian::{ByteOrderChoice, Endian, BigEndian, LittleEndian, NativeEndian, ParseByteOrderError};
+pub use error::{ChannelError, RecvError, SendError};
+pub use handshake::schema_fingerprint;
+pub use header::HeaderEncoding;
+pub use protocol::{call, serve, CallError};
+pub use receiver::{IntoIter, Iter, Receiver, ReceiverBuilder, RecoveryMode, StreamReader, TryIter, DEFAULT_HIGH_WATER_MARK, DEFAULT_MAX_SIZE, DEFAULT_SHRINK_AFTER};
+pub use relay::{relay, Direction, DirectionStats, Relay, RelayStats};
+pub use router::{MessageKind, Route, RouteError, Router};
 pub use sender::{Sender, SenderBuilder};
+pub use stats::{ReceiverStats, SenderStats};
+#[cfg(feature = "derive")]
+pub use tcp_channel_derive::{protocol, MessageKind};
+
+// Used by the code generated by `protocol`.
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+pub mod __private {
+    pub extern crate serde;
+}
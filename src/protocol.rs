@@ -0,0 +1,56 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::{ChannelRecv, ChannelSend, ForwardError};
+
+/// The error of a call made with `call`, or with a client generated by `protocol`.
+#[derive(Debug)]
+pub enum CallError<S, R> {
+    /// Sending the request failed.
+    Send(S),
+    /// Receiving the response failed.
+    Recv(R),
+    /// The response doesn't belong to the request, which means that the other side doesn't
+    /// follow the protocol.
+    UnexpectedResponse,
+}
+impl<S: fmt::Display, R: fmt::Display> fmt::Display for CallError<S, R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CallError::Send(err) => write!(f, "failed to send request: {}", err),
+            CallError::Recv(err) => write!(f, "failed to receive response: {}", err),
+            CallError::UnexpectedResponse => write!(f, "unexpected response"),
+        }
+    }
+}
+impl<S: Error, R: Error> Error for CallError<S, R> {}
+
+/// Send a request, flush it, and wait for the response.
+pub fn call<T, U, S, R>(sender: &mut S, receiver: &mut R, request: &T) -> Result<U, CallError<S::Error, R::Error>>
+where
+    S: ChannelSend<T>,
+    R: ChannelRecv<U>,
+{
+    sender.send(request).map_err(CallError::Send)?;
+    sender.flush().map_err(CallError::Send)?;
+    receiver.recv().map_err(CallError::Recv)
+}
+
+/// Answer every request from `receiver` with the response returned by `handler`, until the other
+/// side disconnects. Responses are flushed as soon as they are sent.
+pub fn serve<T, U, R, S, F>(mut receiver: R, mut sender: S, mut handler: F) -> Result<(), ForwardError<R::Error, S::Error>>
+where
+    R: ChannelRecv<T>,
+    S: ChannelSend<U>,
+    F: FnMut(T) -> U,
+{
+    loop {
+        let request = match receiver.recv() {
+            Ok(request) => request,
+            Err(ref error) if receiver.is_disconnected(error) => return Ok(()),
+            Err(error) => return Err(ForwardError::Recv(error)),
+        };
+        sender.send(&handler(request)).map_err(ForwardError::Send)?;
+        sender.flush().map_err(ForwardError::Send)?;
+    }
+}
@@ -1,28 +1,137 @@
-use std::io::{BufReader, Read};
-use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::io::{BufReader, ErrorKind as IoErrorKind, Read};
+use std::ops::Range;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
 use std::marker::PhantomData;
+use std::time::Instant;
 
-use bincode::Config;
-use byteorder::ReadBytesExt;
+use serde::Deserialize;
 use serde::de::DeserializeOwned;
 
-use crate::{ChannelRecv, Endian, BigEndian, RecvError};
+use crate::handshake::{self, Handshake, Schema};
+use crate::header::MAX_HEADER_LENGTH;
+use crate::{ByteOrderChoice, ChannelRecv, Codec, Endian, BigEndian, HeaderEncoding, IntEncoding, ReceiverStats, RecvError, TrailingBytes};
 
 pub const DEFAULT_MAX_SIZE: usize = 64 * 0x100_000;
+pub const DEFAULT_HIGH_WATER_MARK: usize = 0x100_000;
+pub const DEFAULT_SHRINK_AFTER: usize = 16;
+
+// The smallest amount the receive buffer grows by.
+const MIN_GROWTH: usize = 4096;
+
+// The size of the stack buffer used to skip frames.
+const SKIP_CHUNK_SIZE: usize = 4096;
+
+/// What a receiver does after receiving a frame larger than its max size, in addition to
+/// returning `RecvError::TooLarge`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RecoveryMode {
+    /// Stop receiving, since the stream can't be trusted anymore. Every later call fails with
+    /// `RecvError::Poisoned`. This is the default.
+    #[default]
+    Poison,
+    /// Skip the payload of the frame without storing it, and continue with the next frame.
+    SkipFrame,
+}
 
 /// The receiving side of a channel.
+///
+/// If the reader fails with `WouldBlock` or `TimedOut`, the error is returned, and the next call
+/// that receives resumes where this one left off, so nonblocking readers and read timeouts can be
+/// used. `Interrupted` is retried internally. After any other I/O error, including an unexpected
+/// EOF, the position in the stream is unknown, so the receiver is poisoned, and later calls fail
+/// with `RecvError::Poisoned`.
 pub struct Receiver<T: DeserializeOwned, E: Endian, R: Read = BufReader<TcpStream>> {
     reader: R,
-    config: Config,
-    max_size: usize,
+    codec: Codec,
+    options: Options,
     _marker: PhantomData<(T, E)>,
 
     // This buffer is used for storing the currently read bytes in case the stream is nonblocking.
     // Otherwise, bincode would deserialize only the currently read bytes.
+    //
+    // It only grows as bytes arrive, so that a large length header alone can't force a large
+    // allocation. Its length is the zero-initialized part, which may be more than `bytes_read`.
     buffer: Vec<u8>,
 
+    state: State,
     bytes_read: usize,
     bytes_to_read: usize,
+    bytes_to_skip: u64,
+
+    header: [u8; MAX_HEADER_LENGTH],
+    header_read: usize,
+    // The length of the header of the current frame.
+    header_length: usize,
+
+    // The number of consecutive frames no larger than the high-water mark, while the buffer is
+    // larger than it.
+    small_frames: usize,
+
+    // While a stream is being received, the part of the buffer containing the bytes of the
+    // current chunk that haven't been read yet.
+    stream: Option<Range<usize>>,
+
+    stats: ReceiverStats,
+
+    peer: Option<SocketAddr>,
+    // When the first attempt to read the payload of the current frame was made.
+    payload_started: Instant,
+}
+
+// What the receiver is currently reading.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    HandshakePrefix,
+    HandshakeBody,
+    Header,
+    Payload,
+    Skip,
+    Poisoned,
+}
+
+#[derive(Clone, Copy)]
+struct Options {
+    byte_order: ByteOrderChoice,
+    max_size: usize,
+    header: HeaderEncoding,
+    int_encoding: IntEncoding,
+    trailing_bytes: TrailingBytes,
+    initial_capacity: usize,
+    high_water_mark: usize,
+    shrink_after: usize,
+    handshake: bool,
+    schema: Option<Schema>,
+    recovery: RecoveryMode,
+    peer: Option<SocketAddr>,
+}
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            byte_order: ByteOrderChoice::default(),
+            max_size: DEFAULT_MAX_SIZE,
+            header: HeaderEncoding::default(),
+            int_encoding: IntEncoding::default(),
+            trailing_bytes: TrailingBytes::default(),
+            initial_capacity: 0,
+            high_water_mark: DEFAULT_HIGH_WATER_MARK,
+            shrink_after: DEFAULT_SHRINK_AFTER,
+            handshake: false,
+            schema: None,
+            recovery: RecoveryMode::default(),
+            peer: None,
+        }
+    }
+}
+impl Options {
+    // The max size doubles as the limit of bincode, so that lengths inside a message can't make
+    // it allocate more than a message may take. Serde still reserves up to 1 MiB for sequences
+    // based on their claimed length, before finding out that it is bogus.
+    fn codec(&self) -> Codec {
+        Codec::new(self.byte_order)
+            .with_int_encoding(self.int_encoding)
+            .with_trailing_bytes(self.trailing_bytes)
+            .with_limit(Some(self.max_size as u64))
+    }
 }
 
 /// A more convenient way of initializing receivers.
@@ -30,10 +139,11 @@ pub struct ReceiverBuilder;
 
 pub struct TypedReceiverBuilder<T, R, E> {
     _marker: PhantomData<(T, R, E)>,
-    max_size: usize,
+    options: Options,
 }
 impl ReceiverBuilder {
     /// Begin building a new, buffered channel.
+    #[allow(clippy::new_ret_no_self)]
     pub fn new() -> TypedReceiverBuilder<(), BufReader<TcpStream>, BigEndian> {
         Self::buffered()
     }
@@ -41,14 +151,14 @@ impl ReceiverBuilder {
     pub fn buffered() -> TypedReceiverBuilder<(), BufReader<TcpStream>, BigEndian> {
         TypedReceiverBuilder {
             _marker: PhantomData,
-            max_size: DEFAULT_MAX_SIZE,
+            options: Options::default(),
         }
     }
     /// Begin building a new, non-buffered channel.
     pub fn realtime() -> TypedReceiverBuilder<(), TcpStream, BigEndian> {
         TypedReceiverBuilder {
             _marker: PhantomData,
-            max_size: DEFAULT_MAX_SIZE,
+            options: Options::default(),
         }
     }
 }
@@ -57,29 +167,111 @@ impl<T, R, E> TypedReceiverBuilder<T, R, E> {
     pub fn with_type<U: DeserializeOwned>(self) -> TypedReceiverBuilder<U, R, E> {
         TypedReceiverBuilder {
             _marker: PhantomData,
-            max_size: self.max_size,
+            options: self.options,
         }
     }
     /// Specify the underlying reader type.
     pub fn with_reader<S: Read>(self) -> TypedReceiverBuilder<T, S, E> {
         TypedReceiverBuilder {
             _marker: PhantomData,
-            max_size: self.max_size,
+            options: self.options,
         }
     }
     /// Specify the endianness.
-    pub fn with_endianness<F: Endian>(self) -> TypedReceiverBuilder<T, R, F> {
+    pub fn with_endianness<F: Endian>(mut self) -> TypedReceiverBuilder<T, R, F> {
+        self.options.byte_order = F::byte_order();
         TypedReceiverBuilder {
             _marker: PhantomData,
-            max_size: self.max_size,
+            options: self.options,
         }
     }
+    /// Specify the endianness at runtime, overriding the type parameter. With a handshake, the
+    /// endianness announced by the sender is used instead.
+    pub fn with_byte_order(mut self, byte_order: ByteOrderChoice) -> Self {
+        self.options.byte_order = byte_order;
+        self
+    }
     /// Specify the max size to be allocated when receiving.
-    pub fn with_max_size(self, max_size: usize) -> Self {
-        Self {
-            _marker: PhantomData,
-            max_size,
-        }
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.options.max_size = max_size;
+        self
+    }
+    /// Specify how the length of each message is encoded. With a handshake, the encoding
+    /// announced by the sender is used instead.
+    pub fn with_header_encoding(mut self, header: HeaderEncoding) -> Self {
+        self.options.header = header;
+        self
+    }
+    /// Specify how bincode encodes integers. With a handshake, the encoding announced by the
+    /// sender is used instead. Defaults to `IntEncoding::Fixint`.
+    pub fn with_int_encoding(mut self, int_encoding: IntEncoding) -> Self {
+        self.options.int_encoding = int_encoding;
+        self
+    }
+    /// Specify whether bytes left over after deserializing a message are an error. Defaults to
+    /// `TrailingBytes::Allow`.
+    pub fn with_trailing_bytes(mut self, trailing_bytes: TrailingBytes) -> Self {
+        self.options.trailing_bytes = trailing_bytes;
+        self
+    }
+    /// Specify the whole bincode configuration at once, overriding the endianness, integer
+    /// encoding and trailing bytes behavior. The limit of the codec is ignored, since the max
+    /// size applies instead.
+    pub fn with_codec(mut self, codec: Codec) -> Self {
+        self.options.byte_order = codec.byte_order();
+        self.options.int_encoding = codec.int_encoding();
+        self.options.trailing_bytes = codec.trailing_bytes();
+        self
+    }
+    /// Specify what happens after receiving a message larger than the max size. Defaults to
+    /// `RecoveryMode::Poison`.
+    pub fn with_recovery(mut self, recovery: RecoveryMode) -> Self {
+        self.options.recovery = recovery;
+        self
+    }
+    /// Specify the number of bytes to allocate for the receive buffer up front.
+    pub fn with_initial_capacity(mut self, initial_capacity: usize) -> Self {
+        self.options.initial_capacity = initial_capacity;
+        self
+    }
+    /// Specify the receive buffer size above which the buffer is shrunk again, once enough
+    /// smaller messages have been received. Defaults to `DEFAULT_HIGH_WATER_MARK`.
+    pub fn with_high_water_mark(mut self, high_water_mark: usize) -> Self {
+        self.options.high_water_mark = high_water_mark;
+        self
+    }
+    /// Specify how many consecutive messages no larger than the high-water mark have to be
+    /// received, before a buffer larger than it is shrunk. Defaults to `DEFAULT_SHRINK_AFTER`.
+    pub fn with_shrink_after(mut self, messages: usize) -> Self {
+        self.options.shrink_after = messages;
+        self
+    }
+    /// Expect a handshake from the sender before the first message, as written when using
+    /// `TypedSenderBuilder::with_handshake`. Receiving fails with `RecvError::MaxSizeMismatch` if
+    /// the sender allows larger messages than this receiver does.
+    pub fn with_handshake(mut self) -> Self {
+        self.options.handshake = true;
+        self
+    }
+    /// Expect the sender to announce the fingerprint of the message type, derived from its name
+    /// and the version using `schema_fingerprint`. Receiving fails with
+    /// `RecvError::SchemaMismatch` if the fingerprint differs or is missing. This enables the
+    /// handshake.
+    pub fn with_schema_version(mut self, version: u64) -> Self {
+        self.options.schema = Some(Schema::Version(version));
+        self.with_handshake()
+    }
+    /// Expect the sender to announce an arbitrary fingerprint of the message type, instead of
+    /// one derived from its name. This enables the handshake.
+    pub fn with_fingerprint(mut self, fingerprint: u64) -> Self {
+        self.options.schema = Some(Schema::Fingerprint(fingerprint));
+        self.with_handshake()
+    }
+    /// Specify the address of the sender, which is attached to the spans of the `tracing`
+    /// feature. `listen_once` sets it to the address of the accepted connection.
+    pub fn with_peer_addr(mut self, peer: SocketAddr) -> Self {
+        self.options.peer = Some(peer);
+        self
     }
 }
 impl<T: DeserializeOwned, R: Read, E: Endian> TypedReceiverBuilder<T, R, E> {
@@ -88,11 +280,21 @@ impl<T: DeserializeOwned, R: Read, E: Endian> TypedReceiverBuilder<T, R, E> {
         Receiver {
             _marker: PhantomData,
             reader,
-            config: E::config(),
-            max_size: self.max_size,
-            buffer: Vec::new(),
+            codec: self.options.codec(),
+            options: self.options,
+            buffer: vec! [0; self.options.initial_capacity],
+            state: if self.options.handshake { State::HandshakePrefix } else { State::Header },
             bytes_read: 0,
-            bytes_to_read: 0,
+            bytes_to_read: if self.options.handshake { handshake::PREFIX_LENGTH } else { 0 },
+            bytes_to_skip: 0,
+            header: [0; MAX_HEADER_LENGTH],
+            header_read: 0,
+            header_length: 0,
+            small_frames: 0,
+            stream: None,
+            stats: ReceiverStats::default(),
+            peer: self.options.peer,
+            payload_started: Instant::now(),
         }
     }
 }
@@ -101,17 +303,12 @@ impl<T: DeserializeOwned, E: Endian> TypedReceiverBuilder<T, BufReader<TcpStream
     pub fn listen_once<A: ToSocketAddrs>(self, address: A) -> std::io::Result<Receiver<T, E, BufReader<TcpStream>>> {
         let listener = TcpListener::bind(address)?;
 
-        let (stream, _) = listener.accept()?;
+        let (stream, peer) = listener.accept()?;
+        event!(debug, %peer, "accepted");
 
-        Ok(Receiver {
-            config: E::config(),
-            _marker: PhantomData,
-            reader: BufReader::new(stream),
-            max_size: self.max_size,
-            buffer: Vec::new(),
-            bytes_read: 0,
-            bytes_to_read: 0,
-        })
+        let mut receiver = self.build(BufReader::new(stream));
+        receiver.peer = Some(peer);
+        Ok(receiver)
     }
 }
 impl<T: DeserializeOwned, E: Endian> TypedReceiverBuilder<T, TcpStream, E> {
@@ -119,17 +316,12 @@ impl<T: DeserializeOwned, E: Endian> TypedReceiverBuilder<T, TcpStream, E> {
     pub fn listen_once<A: ToSocketAddrs>(self, address: A) -> std::io::Result<Receiver<T, E, TcpStream>> {
         let listener = TcpListener::bind(address)?;
 
-        let (stream, _) = listener.accept()?;
+        let (stream, peer) = listener.accept()?;
+        event!(debug, %peer, "accepted");
 
-        Ok(Receiver {
-            config: E::config(),
-            _marker: PhantomData,
-            reader: stream,
-            max_size: self.max_size,
-            buffer: Vec::new(),
-            bytes_read: 0,
-            bytes_to_read: 0,
-        })
+        let mut receiver = self.build(stream);
+        receiver.peer = Some(peer);
+        Ok(receiver)
     }
 }
 
@@ -137,33 +329,370 @@ impl<T: DeserializeOwned, E: Endian, R: Read> ChannelRecv<T> for Receiver<T, E,
     type Error = RecvError;
 
     fn recv(&mut self) -> Result<T, RecvError> {
-        if self.bytes_to_read == 0 {
-            let length = self.reader.read_u64::<E>()? as usize;
-            if length > self.max_size {
-                return Err(RecvError::TooLarge(length))
+        let _span = span!("recv", T, self.peer);
+        self.finish_stream()?;
+        let length = self.fill_frame()?;
+        decode(&self.codec, &self.buffer[..length], &mut self.stats)
+    }
+    fn is_disconnected(&self, error: &RecvError) -> bool {
+        matches!(error, RecvError::Disconnected)
+    }
+}
+
+impl<T: DeserializeOwned, E: Endian, R: Read> Receiver<T, E, R> {
+    // Read the next frame into the buffer, returning its length. If this fails with a non-fatal
+    // error, such as `WouldBlock`, calling it again resumes where it left off. Errors after which
+    // the position in the stream is unknown poison the receiver.
+    fn fill_frame(&mut self) -> Result<usize, RecvError> {
+        let result = self.read_frame();
+        match result {
+            Err(RecvError::InvalidHeader)
+            | Err(RecvError::InvalidHandshake)
+            | Err(RecvError::MaxSizeMismatch(..))
+            | Err(RecvError::SchemaMismatch(..)) => self.state = State::Poisoned,
+            Err(RecvError::TooLarge(_)) if self.options.recovery == RecoveryMode::Poison => self.state = State::Poisoned,
+            Err(RecvError::IoError(ref error)) if !is_transient(error.kind()) => self.state = State::Poisoned,
+            _ => (),
+        }
+        #[cfg(feature = "tracing")]
+        match result {
+            Ok(_) => (),
+            Err(RecvError::Disconnected) => event!(debug, "disconnected"),
+            Err(RecvError::IoError(ref error)) if error.kind() == IoErrorKind::WouldBlock => event!(trace, "would block"),
+            Err(ref error) => event!(warn, %error, poisoned = self.state == State::Poisoned, "failed to receive frame"),
+        }
+        result
+    }
+    fn read_frame(&mut self) -> Result<usize, RecvError> {
+        loop {
+            match self.state {
+                State::HandshakePrefix => {
+                    match self.fill_buffer() {
+                        // The sender only sends the handshake along with the first frame, so it
+                        // may disconnect cleanly without sending it.
+                        Err(RecvError::IoError(ref error)) if error.kind() == IoErrorKind::UnexpectedEof && self.bytes_read == 0 => {
+                            return Err(RecvError::Disconnected)
+                        },
+                        result => result?,
+                    }
+                    self.bytes_to_read = Handshake::decode_prefix(&self.buffer[..handshake::PREFIX_LENGTH])?;
+                    self.bytes_read = 0;
+                    self.state = State::HandshakeBody;
+                },
+                State::HandshakeBody => {
+                    self.fill_buffer()?;
+                    let handshake = Handshake::decode_body(&self.buffer[..self.bytes_to_read])?;
+                    event!(debug, ?handshake, "received handshake");
+                    self.state = State::Header;
+
+                    if let Some(header) = handshake.header {
+                        self.options.header = header;
+                    }
+                    if let Some(byte_order) = handshake.byte_order {
+                        self.options.byte_order = byte_order;
+                    }
+                    if let Some(int_encoding) = handshake.int_encoding {
+                        self.options.int_encoding = int_encoding;
+                    }
+                    self.codec = self.options.codec();
+                    if let Some(schema) = self.options.schema {
+                        let fingerprint = schema.fingerprint::<T>();
+                        if handshake.fingerprint != Some(fingerprint) {
+                            return Err(RecvError::SchemaMismatch(handshake.fingerprint, fingerprint))
+                        }
+                    }
+                    if let Some(max_size) = handshake.max_size {
+                        if max_size > self.options.max_size as u64 {
+                            return Err(RecvError::MaxSizeMismatch(max_size as usize, self.options.max_size))
+                        }
+                    }
+                },
+                State::Header => {
+                    self.shrink_buffer();
+
+                    let length = self.read_header()?;
+                    if length > self.options.max_size as u64 {
+                        self.stats.record_too_large();
+                        self.bytes_to_skip = length;
+                        self.state = State::Skip;
+                        return Err(RecvError::TooLarge(length as usize))
+                    }
+
+                    self.bytes_to_read = length as usize;
+                    self.bytes_read = 0;
+                    self.state = State::Payload;
+                    self.payload_started = Instant::now();
+                },
+                State::Payload => {
+                    self.fill_buffer()?;
+                    self.state = State::Header;
+
+                    let length = self.bytes_to_read;
+                    self.stats.record_frame(self.header_length, length);
+                    event!(trace, size = length, elapsed = ?self.payload_started.elapsed(), "received frame");
+                    if self.buffer.len() > self.high_water_mark() && length <= self.high_water_mark() {
+                        self.small_frames += 1;
+                    } else {
+                        self.small_frames = 0;
+                    }
+
+                    return Ok(length)
+                },
+                State::Skip => {
+                    let mut scratch = [0; SKIP_CHUNK_SIZE];
+                    while self.bytes_to_skip > 0 {
+                        let size = self.bytes_to_skip.min(SKIP_CHUNK_SIZE as u64) as usize;
+                        match self.reader.read(&mut scratch[..size]) {
+                            Ok(0) => return Err(std::io::Error::from(IoErrorKind::UnexpectedEof).into()),
+                            Ok(size) => self.bytes_to_skip -= size as u64,
+                            Err(ref error) if error.kind() == IoErrorKind::Interrupted => (),
+                            Err(error) => return Err(error.into()),
+                        }
+                    }
+                    self.state = State::Header;
+                },
+                State::Poisoned => return Err(RecvError::Poisoned),
+            }
+        }
+    }
+    // Read the header of the next frame, returning the length of its payload.
+    fn read_header(&mut self) -> Result<u64, RecvError> {
+        loop {
+            let remaining = self.options.header.remaining(&self.header[..self.header_read]);
+            if remaining == 0 {
+                break
+            }
+            if self.header_read + remaining > MAX_HEADER_LENGTH {
+                return Err(RecvError::InvalidHeader)
+            }
+
+            match self.reader.read(&mut self.header[self.header_read..self.header_read + remaining]) {
+                Ok(0) if self.header_read == 0 => return Err(RecvError::Disconnected),
+                Ok(0) => return Err(std::io::Error::from(IoErrorKind::UnexpectedEof).into()),
+                Ok(size) => self.header_read += size,
+                Err(ref error) if error.kind() == IoErrorKind::Interrupted => (),
+                Err(error) => return Err(error.into()),
             }
+        }
 
-            if self.buffer.len() < length {
-                self.buffer.extend(std::iter::repeat(0).take(length - self.buffer.len()));
+        let header = &self.header[..self.header_read];
+        self.header_length = self.header_read;
+        self.header_read = 0;
+        self.options.header.decode(self.options.byte_order, header).ok_or(RecvError::InvalidHeader)
+    }
+    // Read into the buffer until it contains `bytes_to_read` bytes.
+    fn fill_buffer(&mut self) -> Result<(), RecvError> {
+        while self.bytes_read < self.bytes_to_read {
+            if self.bytes_read == self.buffer.len() {
+                // Grow the buffer by doubling, but never beyond what is being read.
+                let growth = self.buffer.len().max(MIN_GROWTH).min(self.bytes_to_read - self.buffer.len());
+                self.buffer.reserve_exact(growth);
+                self.buffer.resize(self.buffer.len() + growth, 0);
             }
+            let end = self.buffer.len().min(self.bytes_to_read);
 
-            self.bytes_to_read = length;
-            self.bytes_read = 0;
+            match self.reader.read(&mut self.buffer[self.bytes_read..end]) {
+                Ok(0) => return Err(std::io::Error::from(IoErrorKind::UnexpectedEof).into()),
+                Ok(size) => self.bytes_read += size,
+                Err(ref error) if error.kind() == IoErrorKind::Interrupted => (),
+                Err(error) => return Err(error.into()),
+            }
         }
+        Ok(())
+    }
+    /// The traffic through this receiver so far.
+    pub fn stats(&self) -> ReceiverStats {
+        self.stats
+    }
+    /// The address of the sender, if the receiver was created using `listen_once`, or it was
+    /// specified using `TypedReceiverBuilder::with_peer_addr`.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.peer
+    }
+    fn high_water_mark(&self) -> usize {
+        self.options.high_water_mark.max(self.options.initial_capacity)
+    }
+    // Shrink the buffer if it has been above the high-water mark for long enough. This happens
+    // before reading the next frame rather than after the previous one, since the previous
+    // message may still have been borrowing from the buffer.
+    fn shrink_buffer(&mut self) {
+        if self.small_frames > 0 && self.small_frames >= self.options.shrink_after {
+            self.buffer.truncate(self.high_water_mark());
+            self.buffer.shrink_to_fit();
+            self.small_frames = 0;
+        }
+    }
+    /// The number of bytes currently allocated for the receive buffer.
+    pub fn buffer_capacity(&self) -> usize {
+        self.buffer.capacity()
+    }
+    /// Receive a message that borrows from the receive buffer, instead of the owned `T`.
+    ///
+    /// This avoids copying large `&[u8]` and `&str` fields out of the buffer. The message has to
+    /// be dropped before the receiver can be used again.
+    pub fn recv_borrowed<'a, U: Deserialize<'a>>(&'a mut self) -> Result<U, RecvError> {
+        let _span = span!("recv_borrowed", U, self.peer);
+        self.finish_stream()?;
+        let length = self.fill_frame()?;
+        decode(&self.codec, &self.buffer[..length], &mut self.stats)
+    }
+    /// Receive the payload of the next frame without deserializing it, for example to forward it
+    /// using `Sender::send_raw`, or to decode it with a codec of your own.
+    pub fn recv_raw(&mut self) -> Result<&[u8], RecvError> {
+        let _span = span!("recv_raw", T, self.peer);
+        self.finish_stream()?;
+        let length = self.fill_frame()?;
+        Ok(&self.buffer[..length])
+    }
+    /// Receive a stream of bytes, as sent by `Sender::send_stream`. The stream is read from the
+    /// returned reader, until it returns EOF.
+    ///
+    /// Streams aren't distinguishable from messages on the wire, so the protocol has to announce
+    /// them, typically using a preceding message. If the reader is dropped before the end of the
+    /// stream, the rest of it is skipped when receiving the next message.
+    pub fn recv_stream(&mut self) -> Result<StreamReader<'_, T, E, R>, RecvError> {
+        let _span = span!("recv_stream", T, self.peer);
+        self.finish_stream()?;
+        self.stream = Some(0..0);
+
+        Ok(StreamReader {
+            receiver: self,
+        })
+    }
+    // Skip the remaining chunks of the current stream, if any.
+    fn finish_stream(&mut self) -> Result<(), RecvError> {
+        while self.stream.is_some() {
+            if self.fill_frame()? == 0 {
+                self.stream = None;
+            }
+        }
+        Ok(())
+    }
+    /// Iterate over the received messages, blocking if the reader blocks. The iterator ends when
+    /// the sender disconnects or the receiver is poisoned; any other error is yielded, and
+    /// receiving continues afterwards.
+    pub fn iter(&mut self) -> Iter<'_, T, E, R> {
+        Iter {
+            receiver: self,
+        }
+    }
+    /// Iterate over the messages that can be received without blocking. In addition to ending
+    /// when the sender disconnects, the iterator ends when the reader returns `WouldBlock`.
+    pub fn try_iter(&mut self) -> TryIter<'_, T, E, R> {
+        TryIter {
+            receiver: self,
+        }
+    }
+}
 
+// Deserialize a frame. This borrows the fields it needs separately, so that the message can
+// borrow from the buffer while the stats are updated.
+fn decode<'a, U: Deserialize<'a>>(codec: &Codec, frame: &'a [u8], stats: &mut ReceiverStats) -> Result<U, RecvError> {
+    let time = Instant::now();
+    let result = codec.deserialize(frame);
+    let elapsed = time.elapsed();
+    stats.record_deserialize_time(elapsed);
+
+    if result.is_ok() {
+        event!(trace, size = frame.len(), ?elapsed, "deserialized message");
+    }
+    result.map_err(|error| {
+        event!(warn, %error, size = frame.len(), ?elapsed, "failed to deserialize message");
+        stats.record_decode_error();
+        RecvError::BincodeError(error, frame.to_vec())
+    })
+}
+
+// Whether an I/O error leaves the reader where it was, so that receiving can be resumed.
+fn is_transient(kind: IoErrorKind) -> bool {
+    kind == IoErrorKind::WouldBlock || kind == IoErrorKind::TimedOut || kind == IoErrorKind::Interrupted
+}
+
+/// A stream of bytes being received, created by `Receiver::recv_stream`.
+pub struct StreamReader<'a, T: DeserializeOwned, E: Endian, R: Read> {
+    receiver: &'a mut Receiver<T, E, R>,
+}
+impl<'a, T: DeserializeOwned, E: Endian, R: Read> Read for StreamReader<'a, T, E, R> {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
         loop {
-            match self.reader.read(&mut self.buffer[self.bytes_read..self.bytes_to_read]) {
-                Ok(size) => {
-                    self.bytes_read += size;
-                    if self.bytes_read >= self.bytes_to_read {
-                        let length = self.bytes_to_read;
-                        self.bytes_to_read = 0;
-                        return Ok(self.config.deserialize(&self.buffer[0..length])?)
-                    }
-                },
-                Err(error) => return Err(error.into()),
+            let chunk = match self.receiver.stream.clone() {
+                Some(chunk) => chunk,
+                None => return Ok(0),
+            };
+            if !chunk.is_empty() {
+                let size = buffer.len().min(chunk.len());
+                buffer[..size].copy_from_slice(&self.receiver.buffer[chunk.start..chunk.start + size]);
+                self.receiver.stream = Some(chunk.start + size..chunk.end);
+                return Ok(size)
             }
+
+            // An empty chunk marks the end of the stream.
+            let _span = span!("recv_stream", T, self.receiver.peer);
+            let length = self.receiver.fill_frame()?;
+            self.receiver.stream = if length == 0 { None } else { Some(0..length) };
         }
+    }
+}
+
+/// An iterator over received messages, created by `Receiver::iter`.
+pub struct Iter<'a, T: DeserializeOwned, E: Endian, R: Read> {
+    receiver: &'a mut Receiver<T, E, R>,
+}
+impl<'a, T: DeserializeOwned, E: Endian, R: Read> Iterator for Iter<'a, T, E, R> {
+    type Item = Result<T, RecvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.receiver.recv() {
+            Err(RecvError::Disconnected) | Err(RecvError::Poisoned) => None,
+            result => Some(result),
+        }
+    }
+}
+
+/// An iterator over the messages that can be received without blocking, created by
+/// `Receiver::try_iter`.
+pub struct TryIter<'a, T: DeserializeOwned, E: Endian, R: Read> {
+    receiver: &'a mut Receiver<T, E, R>,
+}
+impl<'a, T: DeserializeOwned, E: Endian, R: Read> Iterator for TryIter<'a, T, E, R> {
+    type Item = Result<T, RecvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.receiver.recv() {
+            Err(RecvError::Disconnected) | Err(RecvError::Poisoned) => None,
+            Err(RecvError::IoError(ref error)) if error.kind() == IoErrorKind::WouldBlock => None,
+            result => Some(result),
+        }
+    }
+}
+
+/// An owning iterator over received messages, created by `Receiver::into_iter`.
+pub struct IntoIter<T: DeserializeOwned, E: Endian, R: Read> {
+    receiver: Receiver<T, E, R>,
+}
+impl<T: DeserializeOwned, E: Endian, R: Read> Iterator for IntoIter<T, E, R> {
+    type Item = Result<T, RecvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.iter().next()
+    }
+}
+
+impl<T: DeserializeOwned, E: Endian, R: Read> IntoIterator for Receiver<T, E, R> {
+    type Item = Result<T, RecvError>;
+    type IntoIter = IntoIter<T, E, R>;
+
+    fn into_iter(self) -> IntoIter<T, E, R> {
+        IntoIter {
+            receiver: self,
+        }
+    }
+}
+impl<'a, T: DeserializeOwned, E: Endian, R: Read> IntoIterator for &'a mut Receiver<T, E, R> {
+    type Item = Result<T, RecvError>;
+    type IntoIter = Iter<'a, T, E, R>;
 
+    fn into_iter(self) -> Iter<'a, T, E, R> {
+        self.iter()
     }
 }
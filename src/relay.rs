@@ -0,0 +1,192 @@
+use std::io::BufReader;
+use std::net::{Shutdown, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use crate::{ByteOrderChoice, ForwardError, HeaderEncoding, ReceiverBuilder, RecoveryMode, RecvError, SendError, SenderBuilder, DEFAULT_MAX_SIZE};
+
+/// The direction in which a relay forwards a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// From the first connection to the second.
+    AToB,
+    /// From the second connection to the first.
+    BToA,
+}
+
+/// The traffic forwarded by a relay in one direction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DirectionStats {
+    /// The number of frames forwarded.
+    pub frames: u64,
+    /// The number of payload bytes forwarded, excluding headers.
+    pub bytes: u64,
+    /// The number of frames dropped by the filter, or for being too large.
+    pub dropped: u64,
+}
+
+/// The traffic forwarded by a relay, returned once it has closed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RelayStats {
+    pub a_to_b: DirectionStats,
+    pub b_to_a: DirectionStats,
+}
+
+type Filter = dyn Fn(Direction, &[u8]) -> bool + Send + Sync;
+
+/// A more convenient way of configuring relays. Both connections have to use the same header
+/// encoding and endianness, and must not use a handshake.
+pub struct Relay {
+    byte_order: ByteOrderChoice,
+    max_size: usize,
+    header: HeaderEncoding,
+    recovery: RecoveryMode,
+    filter: Option<Arc<Filter>>,
+}
+impl Default for Relay {
+    fn default() -> Self {
+        Self {
+            byte_order: ByteOrderChoice::default(),
+            max_size: DEFAULT_MAX_SIZE,
+            header: HeaderEncoding::default(),
+            recovery: RecoveryMode::default(),
+            filter: None,
+        }
+    }
+}
+impl Relay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Specify the endianness of the frame headers.
+    pub fn with_byte_order(mut self, byte_order: ByteOrderChoice) -> Self {
+        self.byte_order = byte_order;
+        self
+    }
+    /// Specify the max size of a frame. Defaults to `DEFAULT_MAX_SIZE`.
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+    /// Specify how the length of each frame is encoded.
+    pub fn with_header_encoding(mut self, header: HeaderEncoding) -> Self {
+        self.header = header;
+        self
+    }
+    /// Specify what happens to frames larger than the max size. By default, they close the
+    /// relay with `RecvError::TooLarge`; with `RecoveryMode::SkipFrame`, they are dropped.
+    pub fn with_recovery(mut self, recovery: RecoveryMode) -> Self {
+        self.recovery = recovery;
+        self
+    }
+    /// Call `filter` with the payload of every frame before forwarding it, dropping the frame if
+    /// it returns `false`. This can also be used for logging.
+    pub fn with_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(Direction, &[u8]) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+    /// Forward frames between the connections in both directions, until either of them
+    /// disconnects or fails. Both connections are then shut down, and the traffic is returned,
+    /// or the error that closed the relay.
+    pub fn run(self, a: TcpStream, b: TcpStream) -> Result<RelayStats, ForwardError<RecvError, SendError>> {
+        let closing = Arc::new(AtomicBool::new(false));
+        let config = Arc::new(self);
+
+        let b_to_a = {
+            let (source, destination) = (try_clone(&b)?, try_clone(&a)?);
+            let (config, closing) = (Arc::clone(&config), Arc::clone(&closing));
+            thread::spawn(move || config.forward(Direction::BToA, source, destination, &closing))
+        };
+        let a_to_b = config.forward(Direction::AToB, a, b, &closing);
+        let b_to_a = b_to_a.join().expect("relay thread panicked");
+
+        Ok(RelayStats {
+            a_to_b: a_to_b?,
+            b_to_a: b_to_a?,
+        })
+    }
+    // Forward frames in one direction. Once either direction stops, both connections are shut
+    // down, and the error this causes in the other direction is ignored.
+    fn forward(
+        &self,
+        direction: Direction,
+        source: TcpStream,
+        destination: TcpStream,
+        closing: &AtomicBool,
+    ) -> Result<DirectionStats, ForwardError<RecvError, SendError>> {
+        let result = self.forward_frames(direction, &source, &destination);
+
+        let closed_by_peer = closing.swap(true, Ordering::SeqCst);
+        let _ = source.shutdown(Shutdown::Both);
+        let _ = destination.shutdown(Shutdown::Both);
+
+        match result {
+            Err((_, stats)) if closed_by_peer => Ok(stats),
+            Err((error, _)) => Err(error),
+            Ok(stats) => Ok(stats),
+        }
+    }
+    fn forward_frames(
+        &self,
+        direction: Direction,
+        source: &TcpStream,
+        destination: &TcpStream,
+    ) -> Result<DirectionStats, (ForwardError<RecvError, SendError>, DirectionStats)> {
+        let mut stats = DirectionStats::default();
+        let clone = |stream| try_clone(stream).map_err(|error| (error, stats));
+        let peer_addr = |stream: &TcpStream| stream.peer_addr().map_err(|error| (ForwardError::Recv(error.into()), stats));
+
+        let mut receiver = ReceiverBuilder::buffered()
+            .with_type::<()>()
+            .with_byte_order(self.byte_order)
+            .with_max_size(self.max_size)
+            .with_header_encoding(self.header)
+            .with_recovery(self.recovery)
+            .with_peer_addr(peer_addr(source)?)
+            .build(BufReader::new(clone(source)?));
+        let mut sender = SenderBuilder::realtime()
+            .with_type::<()>()
+            .with_byte_order(self.byte_order)
+            .with_max_size(self.max_size)
+            .with_header_encoding(self.header)
+            .with_peer_addr(peer_addr(destination)?)
+            .build(clone(destination)?);
+
+        loop {
+            let frame = match receiver.recv_raw() {
+                Ok(frame) => frame,
+                Err(RecvError::Disconnected) => return Ok(stats),
+                Err(RecvError::TooLarge(_)) if self.recovery == RecoveryMode::SkipFrame => {
+                    stats.dropped += 1;
+                    continue
+                },
+                Err(error) => return Err((ForwardError::Recv(error), stats)),
+            };
+            if let Some(ref filter) = self.filter {
+                if !filter(direction, frame) {
+                    stats.dropped += 1;
+                    continue
+                }
+            }
+            if let Err(error) = sender.send_raw(frame) {
+                return Err((ForwardError::Send(error), stats))
+            }
+            stats.frames += 1;
+            stats.bytes += frame.len() as u64;
+        }
+    }
+}
+
+fn try_clone(stream: &TcpStream) -> Result<TcpStream, ForwardError<RecvError, SendError>> {
+    stream.try_clone().map_err(|error| ForwardError::Recv(error.into()))
+}
+
+/// Forward frames between two connections in both directions, using the default configuration.
+/// See `Relay::run`.
+pub fn relay(a: TcpStream, b: TcpStream) -> Result<RelayStats, ForwardError<RecvError, SendError>> {
+    Relay::new().run(a, b)
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+
+use crate::{ChannelRecv, ChannelSend, SendError};
+
+/// A message type whose values fall into kinds, which `Router` dispatches on. For an enum, the
+/// kind is usually a field-less copy of it, with one variant per variant of the message.
+///
+/// With the `derive` feature, `#[derive(MessageKind)]` generates the kinds of an enum, along
+/// with a `Route` per variant.
+pub trait MessageKind {
+    type Kind: Eq + Hash;
+    fn kind(&self) -> Self::Kind;
+}
+
+/// A kind of message with a payload, which `Router::route` passes to the handler instead of the
+/// whole message.
+pub trait Route<T: MessageKind> {
+    /// What a message of this kind holds, e.g. the fields of an enum variant.
+    type Payload;
+    fn kind(&self) -> T::Kind;
+    /// Take the payload out of a message, which is returned unchanged if it's of another kind.
+    /// Every message of the kind must be accepted.
+    fn payload(&self, message: T) -> Result<Self::Payload, T>;
+}
+
+/// The error that stopped `Router::run`.
+#[derive(Debug)]
+pub enum RouteError<T, R, S> {
+    /// Receiving a message failed.
+    Recv(R),
+    /// A handler failed, or flushing its replies did.
+    Send(S),
+    /// No handler was registered for the kind of a message, and there was no fallback.
+    Unhandled(T),
+}
+impl<T, R: fmt::Display, S: fmt::Display> fmt::Display for RouteError<T, R, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RouteError::Recv(err) => write!(f, "failed to receive: {}", err),
+            RouteError::Send(err) => write!(f, "failed to reply: {}", err),
+            RouteError::Unhandled(_) => write!(f, "no handler for message"),
+        }
+    }
+}
+impl<T: fmt::Debug, R: Error, S: Error> Error for RouteError<T, R, S> {}
+
+type Handler<T, U, E> = dyn FnMut(T, &mut dyn ChannelSend<U, Error = E>) -> Result<(), E> + Send;
+
+/// Dispatches the messages of type `T` received from a channel to handlers registered per kind,
+/// which can reply with messages of type `U` through the paired sender.
+///
+/// `E` is the error type of the sender, which is `SendError` for a `Sender`.
+pub struct Router<T: MessageKind, U, E = SendError> {
+    handlers: HashMap<T::Kind, Box<Handler<T, U, E>>>,
+    fallback: Option<Box<Handler<T, U, E>>>,
+}
+impl<T: MessageKind, U, E> Default for Router<T, U, E> {
+    fn default() -> Self {
+        Self {
+            handlers: HashMap::new(),
+            fallback: None,
+        }
+    }
+}
+impl<T: MessageKind, U, E> Router<T, U, E> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Handle messages of the specified kind, replacing the previous handler for it, if any.
+    pub fn with_handler<F>(mut self, kind: T::Kind, handler: F) -> Self
+    where
+        F: FnMut(T, &mut dyn ChannelSend<U, Error = E>) -> Result<(), E> + Send + 'static,
+    {
+        self.handlers.insert(kind, Box::new(handler));
+        self
+    }
+    /// Handle messages of the kind of `route`, passing their payload to the handler, and replacing
+    /// the previous handler for the kind, if any.
+    pub fn route<R, F>(self, route: R, mut handler: F) -> Self
+    where
+        R: Route<T> + Send + 'static,
+        F: FnMut(R::Payload, &mut dyn ChannelSend<U, Error = E>) -> Result<(), E> + Send + 'static,
+    {
+        let kind = route.kind();
+        self.with_handler(kind, move |message, reply| match route.payload(message) {
+            Ok(payload) => handler(payload, reply),
+            Err(_) => panic!("route rejected a message of its own kind"),
+        })
+    }
+    /// Handle messages of the kinds without a handler. Without a fallback, they stop `run` with
+    /// `RouteError::Unhandled`.
+    pub fn with_fallback<F>(mut self, handler: F) -> Self
+    where
+        F: FnMut(T, &mut dyn ChannelSend<U, Error = E>) -> Result<(), E> + Send + 'static,
+    {
+        self.fallback = Some(Box::new(handler));
+        self
+    }
+    /// Pass a single message to its handler, or to the fallback. The message is returned if
+    /// neither exists.
+    pub fn handle(&mut self, message: T, sender: &mut dyn ChannelSend<U, Error = E>) -> Result<Option<T>, E> {
+        let handler = match self.handlers.get_mut(&message.kind()) {
+            Some(handler) => handler,
+            None => match self.fallback {
+                Some(ref mut fallback) => fallback,
+                None => return Ok(Some(message)),
+            },
+        };
+        handler(message, sender)?;
+        Ok(None)
+    }
+    /// Receive messages and dispatch them until the other side disconnects, flushing the replies
+    /// after every message. Any other error stops the loop, and is returned.
+    pub fn run<R, S>(&mut self, mut receiver: R, mut sender: S) -> Result<(), RouteError<T, R::Error, E>>
+    where
+        R: ChannelRecv<T>,
+        S: ChannelSend<U, Error = E>,
+    {
+        loop {
+            let message = match receiver.recv() {
+                Ok(message) => message,
+                Err(ref error) if receiver.is_disconnected(error) => return Ok(()),
+                Err(error) => return Err(RouteError::Recv(error)),
+            };
+            if let Some(message) = self.handle(message, &mut sender).map_err(RouteError::Send)? {
+                return Err(RouteError::Unhandled(message))
+            }
+            sender.flush().map_err(RouteError::Send)?;
+        }
+    }
+}
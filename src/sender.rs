@@ -1,18 +1,78 @@
-use std::io::{BufWriter, Write};
+use std::borrow::Borrow;
+use std::io::{BufWriter, Error as IoError, ErrorKind as IoErrorKind, Read, Write};
 use std::marker::PhantomData;
-use std::net::{TcpStream, ToSocketAddrs};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Instant;
 
-use bincode::Config;
-use byteorder::WriteBytesExt;
 use serde::Serialize;
 
-use crate::{ChannelSend, Endian, BigEndian, SendError};
+use crate::handshake::{Handshake, Schema};
+use crate::header::MAX_HEADER_LENGTH;
+use crate::{ByteOrderChoice, ChannelSend, Codec, Endian, BigEndian, HeaderEncoding, IntEncoding, SendError, SenderStats, DEFAULT_MAX_SIZE};
+
+// The largest chunk sent by `Sender::send_stream`.
+const STREAM_CHUNK_SIZE: usize = 0x10_000;
 
 /// The sending side of a channel.
+///
+/// If the writer fails with `WouldBlock` or `TimedOut`, the error is returned, but the message
+/// has been queued, and must not be sent again: the part that hasn't been written is written
+/// before anything else by the next call that sends or flushes. `Interrupted` is retried
+/// internally. After any other I/O error, the receiver's position in the stream is unknown, so
+/// the frames that weren't written are dropped, and later calls fail with
+/// `SendError::Poisoned`.
 pub struct Sender<T: Serialize, E: Endian, W: Write = BufWriter<TcpStream>> {
     writer: W,
-    config: Config,
+    codec: Codec,
+    options: Options,
     _marker: PhantomData<(T, E)>,
+
+    // Frames (the header followed by the serialized message) are encoded into this buffer, which
+    // is kept between messages so that sending doesn't allocate once the buffer has grown to fit
+    // the largest message. It is written using a single call, so that an unbuffered `TcpStream`
+    // doesn't send the header and the payload in separate segments.
+    buffer: Vec<u8>,
+    // The part of the buffer that has been written so far, and the end of the part that has to
+    // be written. They differ when a write failed, and are reset once everything is written.
+    written: usize,
+    to_write: usize,
+
+    handshake_pending: bool,
+    poisoned: bool,
+
+    stats: SenderStats,
+    // The frames encoded by the current call, which are added to `pending` once it succeeds.
+    encoded: SenderStats,
+    // The frames that have to be written, which are added to the stats once they are, and when
+    // the first of them was queued.
+    pending: SenderStats,
+    queued_at: Instant,
+
+    peer: Option<SocketAddr>,
+}
+
+#[derive(Clone, Copy)]
+struct Options {
+    byte_order: ByteOrderChoice,
+    max_size: usize,
+    header: HeaderEncoding,
+    int_encoding: IntEncoding,
+    handshake: bool,
+    schema: Option<Schema>,
+    peer: Option<SocketAddr>,
+}
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            byte_order: ByteOrderChoice::default(),
+            max_size: DEFAULT_MAX_SIZE,
+            header: HeaderEncoding::default(),
+            int_encoding: IntEncoding::default(),
+            handshake: false,
+            schema: None,
+            peer: None,
+        }
+    }
 }
 
 /// A more convenient way of initializing senders.
@@ -20,10 +80,12 @@ pub struct SenderBuilder;
 
 pub struct TypedSenderBuilder<T, W, E> {
     _marker: PhantomData<(T, W, E)>,
+    options: Options,
 }
 
 impl SenderBuilder {
     /// Begin building a new, buffered channel.
+    #[allow(clippy::new_ret_no_self)]
     pub fn new() -> TypedSenderBuilder<(), BufWriter<TcpStream>, BigEndian> {
         Self::buffered()
     }
@@ -31,12 +93,14 @@ impl SenderBuilder {
     pub fn buffered() -> TypedSenderBuilder<(), BufWriter<TcpStream>, BigEndian> {
         TypedSenderBuilder {
             _marker: PhantomData,
+            options: Options::default(),
         }
     }
     /// Begin building a new, non-buffered channel.
     pub fn realtime() -> TypedSenderBuilder<(), TcpStream, BigEndian> {
         TypedSenderBuilder {
             _marker: PhantomData,
+            options: Options::default(),
         }
     }
 }
@@ -45,20 +109,80 @@ impl<T, W, E> TypedSenderBuilder<T, W, E> {
     pub fn with_type<U: Serialize>(self) -> TypedSenderBuilder<U, W, E> {
         TypedSenderBuilder {
             _marker: PhantomData,
+            options: self.options,
         }
     }
     /// Specify the underlying writer type.
     pub fn with_writer<X: Write>(self) -> TypedSenderBuilder<T, X, E> {
         TypedSenderBuilder {
             _marker: PhantomData,
+            options: self.options,
         }
     }
     /// Specify the endianness.
-    pub fn with_endianness<F: Endian>(self) -> TypedSenderBuilder<T, W, F> {
+    pub fn with_endianness<F: Endian>(mut self) -> TypedSenderBuilder<T, W, F> {
+        self.options.byte_order = F::byte_order();
         TypedSenderBuilder {
             _marker: PhantomData,
+            options: self.options,
         }
     }
+    /// Specify the endianness at runtime, overriding the type parameter.
+    pub fn with_byte_order(mut self, byte_order: ByteOrderChoice) -> Self {
+        self.options.byte_order = byte_order;
+        self
+    }
+    /// Specify the max size of a serialized message. Larger messages fail with
+    /// `SendError::TooLarge` before anything is written. Defaults to `DEFAULT_MAX_SIZE`, which is
+    /// also the default limit of the receiver.
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.options.max_size = max_size;
+        self
+    }
+    /// Specify how the length of each message is encoded. Messages too large for the encoding
+    /// fail with `SendError::TooLarge`.
+    pub fn with_header_encoding(mut self, header: HeaderEncoding) -> Self {
+        self.options.header = header;
+        self
+    }
+    /// Specify how bincode encodes integers. Defaults to `IntEncoding::Fixint`.
+    pub fn with_int_encoding(mut self, int_encoding: IntEncoding) -> Self {
+        self.options.int_encoding = int_encoding;
+        self
+    }
+    /// Specify the whole bincode configuration at once, overriding the endianness and integer
+    /// encoding. The limit and trailing bytes behavior of the codec only apply to receivers.
+    pub fn with_codec(mut self, codec: Codec) -> Self {
+        self.options.byte_order = codec.byte_order();
+        self.options.int_encoding = codec.int_encoding();
+        self
+    }
+    /// Write a handshake before the first message, announcing the max size, header encoding,
+    /// endianness and integer encoding to the receiver, which has to expect it using
+    /// `TypedReceiverBuilder::with_handshake`.
+    pub fn with_handshake(mut self) -> Self {
+        self.options.handshake = true;
+        self
+    }
+    /// Announce the fingerprint of the message type in the handshake, derived from its name and
+    /// the version using `schema_fingerprint`, so that a receiver expecting another schema fails
+    /// with `RecvError::SchemaMismatch`. This enables the handshake.
+    pub fn with_schema_version(mut self, version: u64) -> Self {
+        self.options.schema = Some(Schema::Version(version));
+        self.with_handshake()
+    }
+    /// Announce an arbitrary fingerprint of the message type in the handshake, instead of one
+    /// derived from its name. This enables the handshake.
+    pub fn with_fingerprint(mut self, fingerprint: u64) -> Self {
+        self.options.schema = Some(Schema::Fingerprint(fingerprint));
+        self.with_handshake()
+    }
+    /// Specify the address of the receiver, which is attached to the spans of the `tracing`
+    /// feature. `connect` sets it to the address it connected to.
+    pub fn with_peer_addr(mut self, peer: SocketAddr) -> Self {
+        self.options.peer = Some(peer);
+        self
+    }
 }
 impl<T: Serialize, W: Write, E: Endian> TypedSenderBuilder<T, W, E> {
     /// Initialize the sender with the current variables.
@@ -66,7 +190,19 @@ impl<T: Serialize, W: Write, E: Endian> TypedSenderBuilder<T, W, E> {
         Sender {
             _marker: PhantomData,
             writer,
-            config: E::config(),
+            codec: Codec::new(self.options.byte_order)
+                .with_int_encoding(self.options.int_encoding),
+            options: self.options,
+            buffer: Vec::new(),
+            written: 0,
+            to_write: 0,
+            handshake_pending: self.options.handshake,
+            poisoned: false,
+            stats: SenderStats::default(),
+            encoded: SenderStats::default(),
+            pending: SenderStats::default(),
+            queued_at: Instant::now(),
+            peer: self.options.peer,
         }
     }
 }
@@ -74,12 +210,12 @@ impl<T: Serialize, E: Endian> TypedSenderBuilder<T, BufWriter<TcpStream>, E> {
     /// Connect to a listening receiver, at a specified address.
     pub fn connect<A: ToSocketAddrs>(self, address: A) -> std::io::Result<Sender<T, E, BufWriter<TcpStream>>> {
         let stream = TcpStream::connect(address)?;
+        let peer = stream.peer_addr().ok();
+        event!(debug, ?peer, "connected");
 
-        Ok(Sender {
-            writer: BufWriter::new(stream),
-            _marker: PhantomData,
-            config: E::config(),
-        })
+        let mut sender = self.build(BufWriter::new(stream));
+        sender.peer = peer;
+        Ok(sender)
     }
 }
 impl<T: Serialize, E: Endian> TypedSenderBuilder<T, TcpStream, E> {
@@ -87,25 +223,258 @@ impl<T: Serialize, E: Endian> TypedSenderBuilder<T, TcpStream, E> {
     pub fn connect<A: ToSocketAddrs>(self, address: A) -> std::io::Result<Sender<T, E, TcpStream>> {
         let stream = TcpStream::connect(address)?;
         stream.set_nodelay(true)?;
+        let peer = stream.peer_addr().ok();
+        event!(debug, ?peer, "connected");
 
-        Ok(Sender {
-            writer: stream,
-            _marker: PhantomData,
-            config: E::config(),
-        })
+        let mut sender = self.build(stream);
+        sender.peer = peer;
+        Ok(sender)
     }
 }
 impl<T: Serialize, E: Endian, W: Write> Sender<T, E, W> {
+    /// Write any frames left over by a failed call, and flush the writer.
     pub fn flush(&mut self) -> std::io::Result<()> {
-        self.writer.flush()
+        let _span = span!("flush", T, self.peer);
+        if self.poisoned {
+            return Err(IoError::other(SendError::Poisoned))
+        }
+        self.write_backlog()?;
+        let result = self.writer.flush();
+        if let Err(ref error) = result {
+            self.poison_on(error);
+        }
+        result
+    }
+    /// The traffic through this sender so far.
+    pub fn stats(&self) -> SenderStats {
+        self.stats
+    }
+    /// The address of the receiver, if the sender was created using `connect`, or it was
+    /// specified using `TypedSenderBuilder::with_peer_addr`.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.peer
+    }
+    // Append a frame containing the value to the buffer.
+    fn encode(&mut self, value: &T) -> Result<(), SendError> {
+        let start = self.buffer.len();
+        let time = Instant::now();
+        // A varint header is as long as the payload requires, so the payload's size is computed
+        // first, rather than moving the payload once the header turns out shorter than reserved.
+        let header = self.options.header;
+        let header_length = match header {
+            HeaderEncoding::Varint => self.codec.serialized_size(value).map(|size| header.encoded_length(size)),
+            header => Ok(header.reserved_length()),
+        };
+        let result = header_length.and_then(|header_length| {
+            self.begin_frame(header_length);
+            self.codec.serialize_into(&mut self.buffer, value).map(|()| header_length)
+        });
+        let elapsed = time.elapsed();
+        self.stats.record_serialize_time(elapsed);
+        let header_length = match result {
+            Ok(header_length) => header_length,
+            Err(error) => {
+                event!(warn, %error, "failed to serialize message");
+                self.stats.record_encode_error();
+                self.buffer.truncate(start);
+                return Err(error.into())
+            },
+        };
+        event!(trace, size = self.buffer.len() - start, ?elapsed, "serialized message");
+        self.finish_frame(start, header_length)
+    }
+    // Reserve space for a header of the specified length, returning where the frame starts.
+    fn begin_frame(&mut self, header_length: usize) -> usize {
+        let start = self.buffer.len();
+        self.buffer.resize(start + header_length, 0);
+        start
+    }
+    // Write the header of the frame that starts at `start`, and whose payload is the rest of the
+    // buffer. If the header is shorter than the reserved space, the payload is moved back.
+    fn finish_frame(&mut self, start: usize, reserved: usize) -> Result<(), SendError> {
+        let length = self.buffer.len() - start - reserved;
+        if length > self.options.max_size || length as u64 > self.options.header.max_length() {
+            event!(warn, size = length, max_size = self.options.max_size, "frame too large");
+            self.stats.record_too_large();
+            self.buffer.truncate(start);
+            return Err(SendError::TooLarge(length))
+        }
+
+        let mut header = [0; MAX_HEADER_LENGTH];
+        let size = self.options.header.encode(self.options.byte_order, length as u64, &mut header);
+        let gap = reserved - size;
+        self.buffer[start + gap..start + reserved].copy_from_slice(&header[..size]);
+        if gap > 0 {
+            self.buffer.drain(start..start + gap);
+        }
+        self.encoded.record_frame(size, length);
+        Ok(())
+    }
+    // Begin the buffer with the handshake if that hasn't been sent yet. Frames left over by a
+    // failed write are kept at the start, so that they are written first.
+    fn begin_buffer(&mut self) -> Result<(), SendError> {
+        if self.poisoned {
+            return Err(SendError::Poisoned)
+        }
+        self.buffer.truncate(self.to_write);
+        self.buffer.drain(..self.written);
+        self.to_write -= self.written;
+        self.written = 0;
+        self.encoded = SenderStats::default();
+
+        if self.handshake_pending {
+            event!(debug, "sending handshake");
+            Handshake {
+                max_size: Some(self.options.max_size as u64),
+                header: Some(self.options.header),
+                byte_order: Some(self.options.byte_order),
+                int_encoding: Some(self.options.int_encoding),
+                fingerprint: self.options.schema.map(Schema::fingerprint::<T>),
+            }.encode(&mut self.buffer);
+        }
+        Ok(())
+    }
+    fn write_buffer(&mut self) -> Result<(), SendError> {
+        if self.to_write == 0 {
+            self.queued_at = Instant::now();
+        }
+        self.pending.add_frames(&self.encoded);
+        self.to_write = self.buffer.len();
+        self.handshake_pending = false;
+        Ok(self.write_backlog()?)
+    }
+    // Write the part of the buffer that hasn't been written yet.
+    fn write_backlog(&mut self) -> std::io::Result<()> {
+        while self.written < self.to_write {
+            match self.writer.write(&self.buffer[self.written..self.to_write]) {
+                Ok(0) => {
+                    self.poisoned = true;
+                    return Err(IoErrorKind::WriteZero.into())
+                },
+                Ok(size) => self.written += size,
+                Err(ref error) if error.kind() == IoErrorKind::Interrupted => continue,
+                Err(error) => {
+                    event!(debug, %error, written = self.written, "failed to write frames");
+                    self.poison_on(&error);
+                    return Err(error)
+                },
+            }
+        }
+        if self.to_write > 0 {
+            event!(trace, frames = self.pending.messages, bytes = self.to_write, elapsed = ?self.queued_at.elapsed(), "wrote frames");
+            self.stats.record_written(&self.pending);
+            self.pending = SenderStats::default();
+            self.written = 0;
+            self.to_write = 0;
+        }
+        Ok(())
+    }
+    // Poison the sender unless the error leaves the unwritten frames to be written later.
+    fn poison_on(&mut self, error: &IoError) {
+        if error.kind() != IoErrorKind::WouldBlock && error.kind() != IoErrorKind::TimedOut {
+            event!(warn, %error, "poisoned");
+            self.poisoned = true;
+        }
+    }
+    /// Send multiple values at once, by encoding all of them first and then writing them using a
+    /// single call. If any of them fails to serialize, or is too large, nothing is sent.
+    pub fn send_batch(&mut self, values: &[T]) -> Result<(), SendError> {
+        let _span = span!("send_batch", T, self.peer);
+        self.begin_buffer()?;
+        for value in values {
+            self.encode(value)?;
+        }
+        self.write_buffer()
+    }
+    /// Send bytes as the payload of a frame, without serializing them. They are received as a
+    /// message if they are a value encoded by the same codec, or using `Receiver::recv_raw`.
+    pub fn send_raw(&mut self, payload: &[u8]) -> Result<(), SendError> {
+        let _span = span!("send_raw", T, self.peer);
+        self.begin_buffer()?;
+        let header_length = self.options.header.encoded_length(payload.len() as u64);
+        let start = self.begin_frame(header_length);
+        self.buffer.extend_from_slice(payload);
+        self.finish_frame(start, header_length)?;
+        self.write_buffer()
+    }
+    /// Send the bytes read from `source` until EOF as a stream, split into chunks no larger than
+    /// the max size, returning the number of bytes sent. The stream is received using
+    /// `Receiver::recv_stream`.
+    ///
+    /// If reading from `source` fails, the stream is ended after the bytes sent so far, and the
+    /// error is returned. The receiver can't tell that the stream was cut short, so protocols that
+    /// need to should send its length first.
+    ///
+    /// A max size of 0 leaves no room for any data, so it fails with `SendError::ZeroMaxSize`
+    /// before anything is read or written.
+    pub fn send_stream<S: Read>(&mut self, mut source: S) -> Result<u64, SendError> {
+        let _span = span!("send_stream", T, self.peer);
+        if self.options.max_size == 0 {
+            return Err(SendError::ZeroMaxSize)
+        }
+        let max_length = self.options.header.max_length().min(STREAM_CHUNK_SIZE as u64) as usize;
+        let chunk_size = self.options.max_size.min(max_length);
+        // A chunk's length is only known once it has been read, so a short read can still leave
+        // a gap before a varint header, but moving the chunk then copies less than a full one.
+        let header_length = self.options.header.encoded_length(chunk_size as u64);
+        let mut total = 0;
+
+        loop {
+            self.begin_buffer()?;
+            let start = self.begin_frame(header_length);
+            let payload_start = self.buffer.len();
+            self.buffer.resize(payload_start + chunk_size, 0);
+
+            let length = loop {
+                match source.read(&mut self.buffer[payload_start..]) {
+                    Ok(length) => break length,
+                    Err(ref error) if error.kind() == IoErrorKind::Interrupted => continue,
+                    Err(error) => {
+                        // End the stream where it is, so that the receiver doesn't take the next
+                        // message for a chunk.
+                        event!(debug, %error, sent = total, "failed to read stream");
+                        self.buffer.truncate(payload_start);
+                        self.finish_frame(start, header_length)?;
+                        self.write_buffer()?;
+                        return Err(error.into())
+                    },
+                }
+            };
+            self.buffer.truncate(payload_start + length);
+            self.finish_frame(start, header_length)?;
+            self.write_buffer()?;
+
+            // The empty chunk, which has now been written, marks the end of the stream.
+            if length == 0 {
+                return Ok(total)
+            }
+            total += length as u64;
+        }
+    }
+    /// Send every value, and then flush the writer once.
+    pub fn send_all<I>(&mut self, values: I) -> Result<(), SendError>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<T>,
+    {
+        for value in values {
+            ChannelSend::send(self, value.borrow())?;
+        }
+        Ok(self.flush()?)
     }
 }
 impl<T: Serialize, E: Endian, W: Write> ChannelSend<T> for Sender<T, E, W> {
     type Error = SendError;
     fn send(&mut self, value: &T) -> Result<(), SendError> {
-        let buffer = self.config.serialize(value)?;
-        self.writer.write_u64::<E>(buffer.len() as u64)?;
-        self.writer.write(&buffer)?;
-        Ok(())
+        let _span = span!("send", T, self.peer);
+        self.begin_buffer()?;
+        self.encode(value)?;
+        self.write_buffer()
+    }
+    fn flush(&mut self) -> Result<(), SendError> {
+        if self.poisoned {
+            return Err(SendError::Poisoned)
+        }
+        Ok(Sender::flush(self)?)
     }
 }
@@ -0,0 +1,122 @@
+// Counters kept by senders and receivers. With the `metrics` feature, every update is also
+// reported to the `metrics` facade, with names starting with `tcp_channel_`.
+
+use std::time::Duration;
+
+/// The traffic through a sender since it was created, returned by `Sender::stats`. The handshake
+/// isn't included.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SenderStats {
+    /// The number of frames written, including raw frames and stream chunks.
+    pub messages: u64,
+    /// The number of bytes written for the frame headers.
+    pub header_bytes: u64,
+    /// The number of bytes written for the frame payloads.
+    pub payload_bytes: u64,
+    /// The time spent serializing messages.
+    pub serialize_time: Duration,
+    /// The payload length of the largest frame written.
+    pub largest_frame: u64,
+    /// The number of frames rejected for being larger than the max size.
+    pub too_large: u64,
+    /// The number of messages that failed to serialize.
+    pub encode_errors: u64,
+}
+impl SenderStats {
+    // Count a frame that has been encoded, but not written yet.
+    pub(crate) fn record_frame(&mut self, header: usize, payload: usize) {
+        self.messages += 1;
+        self.header_bytes += header as u64;
+        self.payload_bytes += payload as u64;
+        self.largest_frame = self.largest_frame.max(payload as u64);
+    }
+    // Add the frames counted by `other`.
+    pub(crate) fn add_frames(&mut self, other: &SenderStats) {
+        self.messages += other.messages;
+        self.header_bytes += other.header_bytes;
+        self.payload_bytes += other.payload_bytes;
+        self.largest_frame = self.largest_frame.max(other.largest_frame);
+    }
+    // Add the frames counted by `pending`, once they have been written.
+    pub(crate) fn record_written(&mut self, pending: &SenderStats) {
+        self.add_frames(pending);
+
+        #[cfg(feature = "metrics")]
+        {
+            metrics::counter!("tcp_channel_messages_sent").increment(pending.messages);
+            metrics::counter!("tcp_channel_header_bytes_sent").increment(pending.header_bytes);
+            metrics::counter!("tcp_channel_payload_bytes_sent").increment(pending.payload_bytes);
+        }
+    }
+    pub(crate) fn record_serialize_time(&mut self, time: Duration) {
+        self.serialize_time += time;
+
+        #[cfg(feature = "metrics")]
+        metrics::histogram!("tcp_channel_serialize_seconds").record(time);
+    }
+    pub(crate) fn record_too_large(&mut self) {
+        self.too_large += 1;
+
+        #[cfg(feature = "metrics")]
+        metrics::counter!("tcp_channel_too_large_sent").increment(1);
+    }
+    pub(crate) fn record_encode_error(&mut self) {
+        self.encode_errors += 1;
+
+        #[cfg(feature = "metrics")]
+        metrics::counter!("tcp_channel_encode_errors").increment(1);
+    }
+}
+
+/// The traffic through a receiver since it was created, returned by `Receiver::stats`. The
+/// handshake isn't included.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReceiverStats {
+    /// The number of frames read, including raw frames and stream chunks.
+    pub messages: u64,
+    /// The number of bytes read for the frame headers.
+    pub header_bytes: u64,
+    /// The number of bytes read for the frame payloads.
+    pub payload_bytes: u64,
+    /// The time spent deserializing messages.
+    pub deserialize_time: Duration,
+    /// The payload length of the largest frame read.
+    pub largest_frame: u64,
+    /// The number of frames rejected for being larger than the max size.
+    pub too_large: u64,
+    /// The number of messages that failed to deserialize.
+    pub decode_errors: u64,
+}
+impl ReceiverStats {
+    pub(crate) fn record_frame(&mut self, header: usize, payload: usize) {
+        self.messages += 1;
+        self.header_bytes += header as u64;
+        self.payload_bytes += payload as u64;
+        self.largest_frame = self.largest_frame.max(payload as u64);
+
+        #[cfg(feature = "metrics")]
+        {
+            metrics::counter!("tcp_channel_messages_received").increment(1);
+            metrics::counter!("tcp_channel_header_bytes_received").increment(header as u64);
+            metrics::counter!("tcp_channel_payload_bytes_received").increment(payload as u64);
+        }
+    }
+    pub(crate) fn record_deserialize_time(&mut self, time: Duration) {
+        self.deserialize_time += time;
+
+        #[cfg(feature = "metrics")]
+        metrics::histogram!("tcp_channel_deserialize_seconds").record(time);
+    }
+    pub(crate) fn record_too_large(&mut self) {
+        self.too_large += 1;
+
+        #[cfg(feature = "metrics")]
+        metrics::counter!("tcp_channel_too_large_received").increment(1);
+    }
+    pub(crate) fn record_decode_error(&mut self) {
+        self.decode_errors += 1;
+
+        #[cfg(feature = "metrics")]
+        metrics::counter!("tcp_channel_decode_errors").increment(1);
+    }
+}
@@ -0,0 +1,193 @@
+//! Utilities for testing code that uses channels under unreliable conditions.
+//!
+//! `FaultyStream` wraps a reader or writer, and injects faults into the bytes going through it,
+//! as configured using `Faults`. The faults are chosen by a pseudorandom generator with a fixed
+//! seed, so that a failing test can be reproduced.
+
+use std::io::{ErrorKind as IoErrorKind, Read, Result as IoResult, Write};
+
+/// The faults injected by a `FaultyStream`. By default, none are.
+///
+/// Probabilities are between 0 and 1, and apply to every call to `read` or `write`, except for
+/// `with_bit_flips`, which applies to every byte.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Faults {
+    seed: u64,
+    short_io: f64,
+    would_block: f64,
+    interrupted: f64,
+    bit_flips: f64,
+    truncate_at: Option<u64>,
+    disconnect_at: Option<u64>,
+}
+impl Faults {
+    /// No faults, using the specified seed once faults are added.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            ..Self::default()
+        }
+    }
+    /// Read or write fewer bytes than requested, with the specified probability.
+    pub fn with_short_io(mut self, probability: f64) -> Self {
+        self.short_io = probability;
+        self
+    }
+    /// Fail with `WouldBlock` without reading or writing, with the specified probability.
+    pub fn with_would_block(mut self, probability: f64) -> Self {
+        self.would_block = probability;
+        self
+    }
+    /// Fail with `Interrupted` without reading or writing, with the specified probability.
+    pub fn with_interrupted(mut self, probability: f64) -> Self {
+        self.interrupted = probability;
+        self
+    }
+    /// Flip a random bit of each byte read or written, with the specified probability.
+    pub fn with_bit_flips(mut self, probability: f64) -> Self {
+        self.bit_flips = probability;
+        self
+    }
+    /// End the stream once `offset` bytes have gone through: reads return EOF, and writes
+    /// succeed without writing anything.
+    pub fn truncate_at(mut self, offset: u64) -> Self {
+        self.truncate_at = Some(offset);
+        self
+    }
+    /// Disconnect once `offset` bytes have gone through: reads fail with `ConnectionReset`, and
+    /// writes with `BrokenPipe`.
+    pub fn disconnect_at(mut self, offset: u64) -> Self {
+        self.disconnect_at = Some(offset);
+        self
+    }
+}
+
+/// A reader or writer that injects faults, created with `FaultyStream::new`.
+pub struct FaultyStream<T> {
+    inner: T,
+    faults: Faults,
+    rng: XorShift,
+    offset: u64,
+}
+impl<T> FaultyStream<T> {
+    pub fn new(inner: T, faults: Faults) -> Self {
+        Self {
+            inner,
+            faults,
+            rng: XorShift::new(faults.seed),
+            offset: 0,
+        }
+    }
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+    /// The number of bytes that have gone through so far.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    fn reached(&self, offset: Option<u64>) -> bool {
+        offset.is_some_and(|offset| self.offset >= offset)
+    }
+    // Decide how many of the `requested` bytes to transfer, if any, or which fault to inject
+    // instead. `Ok(None)` means that the stream has been truncated.
+    fn next_length(&mut self, requested: usize, disconnected: IoErrorKind) -> IoResult<Option<usize>> {
+        if self.reached(self.faults.disconnect_at) {
+            return Err(disconnected.into())
+        }
+        if self.reached(self.faults.truncate_at) {
+            return Ok(None)
+        }
+        if requested == 0 {
+            return Ok(Some(0))
+        }
+        if self.rng.chance(self.faults.interrupted) {
+            return Err(IoErrorKind::Interrupted.into())
+        }
+        if self.rng.chance(self.faults.would_block) {
+            return Err(IoErrorKind::WouldBlock.into())
+        }
+
+        let limit = [self.faults.truncate_at, self.faults.disconnect_at]
+            .iter()
+            .filter_map(|offset| *offset)
+            .map(|offset| offset - self.offset)
+            .fold(requested as u64, u64::min) as usize;
+        if self.rng.chance(self.faults.short_io) {
+            Ok(Some(1 + self.rng.below(limit as u64) as usize))
+        } else {
+            Ok(Some(limit))
+        }
+    }
+    fn flip_bits(&mut self, bytes: &mut [u8]) {
+        if self.faults.bit_flips > 0.0 {
+            for byte in bytes {
+                if self.rng.chance(self.faults.bit_flips) {
+                    *byte ^= 1 << self.rng.below(8);
+                }
+            }
+        }
+    }
+}
+impl<T: Read> Read for FaultyStream<T> {
+    fn read(&mut self, buffer: &mut [u8]) -> IoResult<usize> {
+        let length = match self.next_length(buffer.len(), IoErrorKind::ConnectionReset)? {
+            Some(length) => length,
+            None => return Ok(0),
+        };
+        let size = self.inner.read(&mut buffer[..length])?;
+        self.flip_bits(&mut buffer[..size]);
+        self.offset += size as u64;
+        Ok(size)
+    }
+}
+impl<T: Write> Write for FaultyStream<T> {
+    fn write(&mut self, data: &[u8]) -> IoResult<usize> {
+        let length = match self.next_length(data.len(), IoErrorKind::BrokenPipe)? {
+            Some(length) => length,
+            None => return Ok(data.len()),
+        };
+        let mut data = data[..length].to_vec();
+        self.flip_bits(&mut data);
+        let size = self.inner.write(&data)?;
+        self.offset += size as u64;
+        Ok(size)
+    }
+    fn flush(&mut self) -> IoResult<()> {
+        if self.reached(self.faults.disconnect_at) {
+            return Err(IoErrorKind::BrokenPipe.into())
+        }
+        self.inner.flush()
+    }
+}
+
+// A xorshift64* generator, which is good enough for choosing faults.
+#[derive(Clone, Copy, Debug)]
+struct XorShift(u64);
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // The state must not be zero.
+        XorShift(if seed == 0 { 0x9e37_79b9_7f4a_7c15 } else { seed })
+    }
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+    // A number in `0..bound`, which must not be zero.
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+    fn chance(&mut self, probability: f64) -> bool {
+        // The top 53 bits, as a float in `0.0..1.0`.
+        let sample = (self.next() >> 11) as f64 / (1u64 << 53) as f64;
+        probability > 0.0 && sample < probability
+    }
+}
@@ -0,0 +1,32 @@
+// Instrumentation macros, which forward to `tracing` with the `tracing` feature, and expand to
+// nothing otherwise.
+
+/// Emit an event at the given level, e.g. `event!(debug, size, "received frame")`.
+#[cfg(feature = "tracing")]
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {
+        tracing::$level!($($arg)+)
+    };
+}
+#[cfg(not(feature = "tracing"))]
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {{}};
+}
+
+/// Enter a span for an operation on a message type, with the peer address attached. The span is
+/// exited when the returned guard is dropped.
+#[cfg(feature = "tracing")]
+macro_rules! span {
+    ($name:expr, $message:ty, $peer:expr) => {
+        tracing::debug_span!($name, message = std::any::type_name::<$message>(), peer = ?$peer).entered()
+    };
+}
+#[cfg(not(feature = "tracing"))]
+macro_rules! span {
+    ($name:expr, $message:ty, $peer:expr) => {
+        crate::trace::NoSpan
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoSpan;
@@ -0,0 +1,99 @@
+extern crate tcp_channel;
+
+use std::io::{self, BufReader, Read, Write};
+use std::sync::mpsc;
+use std::thread;
+
+use tcp_channel::{ChannelError, ChannelReader, ChannelRecv, ChannelSend, ChannelWriter, ReceiverBuilder, SenderBuilder};
+
+#[test]
+fn raw_bytes() {
+    let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+
+    let data = (0..100_000).map(|index| index as u8).collect::<Vec<_>>();
+    let mut writer = ChannelWriter::with_chunk_size(sender, 1000);
+    io::copy(&mut &data[..], &mut writer).unwrap();
+    // Dropping flushes the remaining bytes, and disconnects the channel.
+    drop(writer);
+
+    let mut received = Vec::new();
+    ChannelReader::new(receiver).read_to_end(&mut received).unwrap();
+    assert_eq!(received, data);
+}
+
+#[test]
+fn nested_channel() {
+    // A tcp-channel, running over a std channel of byte vectors.
+    let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+
+    let thread = thread::spawn(move || {
+        let mut sender = SenderBuilder::buffered()
+            .with_type::<String>()
+            .with_writer::<ChannelWriter<mpsc::Sender<Vec<u8>>>>()
+            .build(ChannelWriter::new(sender));
+
+        for index in 0..10 {
+            sender.send(&format!("message #{}", index)).unwrap();
+            sender.flush().unwrap();
+        }
+    });
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<String>()
+        .with_reader::<BufReader<ChannelReader<mpsc::Receiver<Vec<u8>>>>>()
+        .build(BufReader::new(ChannelReader::new(receiver)));
+
+    for index in 0..10 {
+        assert_eq!(receiver.recv().unwrap(), format!("message #{}", index));
+    }
+    thread.join().unwrap();
+    assert!(receiver.recv().is_err());
+}
+
+#[test]
+fn write_to_disconnected() {
+    let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+    drop(receiver);
+
+    let mut writer = ChannelWriter::new(sender);
+    writer.write_all(b"Hello").unwrap();
+    assert_eq!(writer.flush().unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+}
+
+// Records the messages it accepts, after failing with each error kind, last first. `WouldBlock` and
+// `TimedOut` accept the message anyway, as a `Sender` does.
+struct Flaky {
+    sent: Vec<Vec<u8>>,
+    errors: Vec<io::ErrorKind>,
+}
+impl ChannelSend<Vec<u8>> for Flaky {
+    type Error = ChannelError;
+    fn send(&mut self, value: &Vec<u8>) -> Result<(), ChannelError> {
+        let error = self.errors.pop();
+        match error {
+            Some(kind) if kind != io::ErrorKind::WouldBlock && kind != io::ErrorKind::TimedOut => (),
+            _ => self.sent.push(value.clone()),
+        }
+        match error {
+            Some(kind) => Err(ChannelError::IoError(kind.into())),
+            None => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn write_errors_keep_nothing_twice() {
+    let errors = vec![io::ErrorKind::TimedOut, io::ErrorKind::WouldBlock, io::ErrorKind::Other];
+    let mut writer = ChannelWriter::with_chunk_size(Flaky { sent: Vec::new(), errors }, 4);
+
+    // Rejected: none of the bytes are kept.
+    assert_eq!(writer.write(b"abcd").unwrap_err().kind(), io::ErrorKind::Other);
+    // Queued by the channel despite the error, so they count as written.
+    assert_eq!(writer.write(b"efgh").unwrap(), 4);
+    assert_eq!(writer.write(b"ij").unwrap(), 2);
+    assert_eq!(writer.flush().unwrap_err().kind(), io::ErrorKind::TimedOut);
+    writer.write_all(b"klmn").unwrap();
+    writer.flush().unwrap();
+
+    assert_eq!(writer.get_ref().sent, vec![b"efgh".to_vec(), b"ij".to_vec(), b"klmn".to_vec()]);
+}
@@ -0,0 +1,61 @@
+extern crate tcp_channel;
+
+use std::io::{Cursor, Result as IoResult, Write};
+
+use tcp_channel::{ChannelSend, ReceiverBuilder, SenderBuilder};
+
+// Records every call to `write`, to check how frames are split into writes.
+#[derive(Default)]
+struct RecordingWriter {
+    writes: Vec<Vec<u8>>,
+}
+impl Write for RecordingWriter {
+    fn write(&mut self, data: &[u8]) -> IoResult<usize> {
+        self.writes.push(data.to_owned());
+        Ok(data.len())
+    }
+    fn flush(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+}
+
+fn decode(bytes: Vec<u8>) -> Vec<String> {
+    ReceiverBuilder::buffered()
+        .with_type::<String>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .build(Cursor::new(bytes))
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn one_write_per_message() {
+    let mut writer = RecordingWriter::default();
+    {
+        let mut sender = SenderBuilder::realtime()
+            .with_type::<String>()
+            .with_writer::<&mut RecordingWriter>()
+            .build(&mut writer);
+        sender.send(&"first".to_owned()).unwrap();
+        sender.send(&"second".to_owned()).unwrap();
+    }
+    assert_eq!(writer.writes.len(), 2);
+    assert_eq!(decode(writer.writes.concat()), vec! ["first", "second"]);
+}
+
+#[test]
+fn one_write_per_batch() {
+    let messages = (0..100).map(|index| format!("message #{}", index)).collect::<Vec<_>>();
+
+    let mut writer = RecordingWriter::default();
+    SenderBuilder::realtime()
+        .with_type::<String>()
+        .with_writer::<&mut RecordingWriter>()
+        .build(&mut writer)
+        .send_batch(&messages)
+        .unwrap();
+
+    assert_eq!(writer.writes.len(), 1);
+    assert_eq!(decode(writer.writes.concat()), messages);
+}
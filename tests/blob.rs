@@ -46,7 +46,7 @@ quick_error! {
         TcpRecvErr(err: tcp_channel::RecvError) {
             from()
         }
-        JoinErr(err: Box<Any + Send + 'static>) {
+        JoinErr(err: Box<dyn Any + Send + 'static>) {
             from()
         }
     }
@@ -58,9 +58,9 @@ fn pretend_blocking_read<T: DeserializeOwned, R: Read>(receiver: &mut TcpReceive
             Ok(value) => return Ok(value),
             Err(RecvError::IoError(ioerror)) => match ioerror.kind() {
                 IoErrorKind::WouldBlock => continue,
-                _ => return Err(RecvError::IoError(ioerror).into()),
+                _ => return Err(RecvError::IoError(ioerror)),
             }
-            Err(error) => return Err(error.into()),
+            Err(error) => return Err(error),
         }
     }
 }
@@ -145,10 +145,8 @@ fn blob(slow: bool, blocking: bool, max_size: usize) -> Result<(), Error> {
     sender.send(&Request::SendBlob(blob.clone())).unwrap();
     sender.flush().unwrap();
 
-    let new_blob = match pretend_blocking_read(&mut receiver).unwrap() {
-        Response::Respond(blob) => blob,
-    };
-    let precalculated_new_blob = blob.into_iter()
+    let Response::Respond(new_blob) = pretend_blocking_read(&mut receiver).unwrap();
+    let precalculated_new_blob = blob.iter()
         .map(|byte| byte.wrapping_add(1))
         .collect::<Box<[u8]>>();
 
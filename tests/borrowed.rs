@@ -0,0 +1,49 @@
+extern crate tcp_channel;
+extern crate serde;
+#[macro_use] extern crate serde_derive;
+
+use std::io::Cursor;
+
+use tcp_channel::{ChannelSend, ReceiverBuilder, SenderBuilder};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Blob {
+    name: String,
+    data: Vec<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BorrowedBlob<'a> {
+    name: &'a str,
+    data: &'a [u8],
+}
+
+#[test]
+fn borrowed() {
+    let mut bytes = Vec::new();
+    {
+        let mut sender = SenderBuilder::buffered()
+            .with_type::<Blob>()
+            .with_writer::<&mut Vec<u8>>()
+            .build(&mut bytes);
+
+        for index in 0..2u8 {
+            sender.send(&Blob {
+                name: format!("blob #{}", index),
+                data: vec! [index; 65_536],
+            }).unwrap();
+        }
+    }
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<Blob>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .build(Cursor::new(bytes));
+
+    for index in 0..2u8 {
+        let blob = receiver.recv_borrowed::<BorrowedBlob>().unwrap();
+        assert_eq!(blob.name, format!("blob #{}", index));
+        assert_eq!(blob.data.len(), 65_536);
+        assert!(blob.data.iter().all(|&byte| byte == index));
+    }
+}
@@ -0,0 +1,70 @@
+extern crate tcp_channel;
+extern crate serde;
+#[macro_use] extern crate serde_derive;
+
+use std::io::{BufReader, BufWriter};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
+
+use tcp_channel::{forward, ReceiverBuilder, SenderBuilder};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum Message {
+    Number(u32),
+    Text(String),
+}
+
+#[test]
+fn std_to_tcp_and_back() {
+    // Messages take the path: std sender -> forwarding thread -> TCP -> forwarding thread -> std receiver.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let outgoing = TcpStream::connect(address).unwrap();
+    let (incoming, _) = listener.accept().unwrap();
+
+    let tcp_sender = SenderBuilder::buffered()
+        .with_type::<Message>()
+        .build(BufWriter::new(outgoing));
+    let tcp_receiver = ReceiverBuilder::buffered()
+        .with_type::<Message>()
+        .build(BufReader::new(incoming));
+
+    let (input, input_receiver) = mpsc::channel();
+    let (output_sender, output) = mpsc::channel();
+
+    let to_tcp = forward(input_receiver, tcp_sender);
+    let from_tcp = forward(tcp_receiver, output_sender);
+
+    let messages = vec! [Message::Number(42), Message::Text("Hello, world!".into()), Message::Number(1337)];
+    for message in &messages {
+        input.send(message.clone()).unwrap();
+    }
+    for message in &messages {
+        assert_eq!(&output.recv().unwrap(), message);
+    }
+
+    // Dropping the input ends the first thread, which closes the TCP stream and ends the second.
+    drop(input);
+    // Both are clean closes, so neither is an error.
+    to_tcp.join().unwrap();
+    from_tcp.join().unwrap();
+}
+
+#[test]
+fn stop() {
+    let (input, input_receiver) = mpsc::channel();
+    let (output_sender, output) = mpsc::channel();
+
+    let handle = forward(input_receiver, output_sender);
+    input.send(1u8).unwrap();
+    assert_eq!(output.recv().unwrap(), 1);
+
+    handle.stop();
+    // If the thread is already blocked receiving, it only stops once the next message has been
+    // forwarded.
+    input.send(2u8).unwrap();
+    handle.join().unwrap();
+
+    assert!(output.iter().all(|value| value == 2));
+}
@@ -0,0 +1,74 @@
+extern crate tcp_channel;
+
+use std::io::{Cursor, ErrorKind as IoErrorKind, Read, Result as IoResult};
+
+use tcp_channel::{ChannelRecv, ReceiverBuilder, RecvError, SenderBuilder};
+
+// Returns `WouldBlock` instead of EOF, like a nonblocking socket whose peer stopped sending.
+struct Stalled(Cursor<Vec<u8>>);
+
+impl Read for Stalled {
+    fn read(&mut self, buffer: &mut [u8]) -> IoResult<usize> {
+        match self.0.read(buffer)? {
+            0 => Err(IoErrorKind::WouldBlock.into()),
+            size => Ok(size),
+        }
+    }
+}
+
+#[test]
+fn large_header_alone_does_not_allocate() {
+    // A header announcing 60 MiB, followed by only a few bytes of payload.
+    let mut bytes = (60u64 * 0x100_000).to_be_bytes().to_vec();
+    bytes.extend_from_slice(&[0; 100]);
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<Vec<u8>>()
+        .with_reader::<Stalled>()
+        .build(Stalled(Cursor::new(bytes)));
+
+    match receiver.recv() {
+        Err(RecvError::IoError(ref error)) if error.kind() == IoErrorKind::WouldBlock => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(receiver.buffer_capacity() <= 0x10_000);
+}
+
+#[test]
+fn shrinks_after_small_messages() {
+    let mut bytes = Vec::new();
+    SenderBuilder::buffered()
+        .with_type::<Vec<u8>>()
+        .with_writer::<&mut Vec<u8>>()
+        .build(&mut bytes)
+        .send_all(&[vec! [1; 100_000], vec! [2; 10], vec! [3; 10], vec! [4; 10]])
+        .unwrap();
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<Vec<u8>>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_high_water_mark(1024)
+        .with_shrink_after(2)
+        .build(Cursor::new(bytes));
+
+    assert_eq!(receiver.recv().unwrap().len(), 100_000);
+    assert!(receiver.buffer_capacity() >= 100_000);
+
+    receiver.recv().unwrap();
+    receiver.recv().unwrap();
+    assert!(receiver.buffer_capacity() >= 100_000);
+
+    receiver.recv().unwrap();
+    assert!(receiver.buffer_capacity() <= 1024);
+}
+
+#[test]
+fn initial_capacity() {
+    let receiver = ReceiverBuilder::buffered()
+        .with_type::<Vec<u8>>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_initial_capacity(8192)
+        .build(Cursor::new(Vec::new()));
+
+    assert!(receiver.buffer_capacity() >= 8192);
+}
@@ -0,0 +1,69 @@
+extern crate tcp_channel;
+
+use std::collections::VecDeque;
+use std::sync::mpsc;
+
+use tcp_channel::{ChannelRecv, ChannelSend};
+
+// Application code written once against the traits.
+fn double_all<R: ChannelRecv<u32>, S: ChannelSend<u32>>(mut receiver: R, mut sender: S, count: usize) {
+    for _ in 0..count {
+        let value = receiver.recv().ok().unwrap();
+        sender.send(&(value * 2)).ok().unwrap();
+    }
+    sender.flush().ok().unwrap();
+}
+
+#[test]
+fn vec_deque() {
+    let mut input = VecDeque::new();
+    for i in 0..3 {
+        input.send(&i).unwrap();
+    }
+    let mut output = VecDeque::new();
+
+    double_all(&mut input, &mut output, 3);
+
+    assert!(input.recv().is_err());
+    assert_eq!(output, vec! [0, 2, 4]);
+}
+
+#[test]
+fn sync_sender() {
+    let (input, input_receiver) = mpsc::sync_channel(3);
+    let (output_sender, output) = mpsc::sync_channel(3);
+    for i in 0..3 {
+        input.send(i).unwrap();
+    }
+
+    double_all(input_receiver, output_sender, 3);
+
+    assert_eq!(output.iter().collect::<Vec<_>>(), vec! [0, 2, 4]);
+}
+
+#[test]
+fn boxed() {
+    let (input, input_receiver) = mpsc::channel();
+    let receiver: Box<dyn ChannelRecv<u32, Error = mpsc::RecvError>> = Box::new(input_receiver);
+    let mut output = VecDeque::new();
+    let sender: Box<dyn ChannelSend<u32, Error = std::convert::Infallible>> = Box::new(&mut output);
+    input.send(21).unwrap();
+
+    double_all(receiver, sender, 1);
+
+    assert_eq!(output.pop_front(), Some(42));
+}
+
+#[cfg(feature = "crossbeam-channel")]
+#[test]
+fn crossbeam() {
+    let (input, input_receiver) = crossbeam_channel::unbounded();
+    let (output_sender, output) = crossbeam_channel::bounded(3);
+    for i in 0..3 {
+        input.send(i).unwrap();
+    }
+
+    double_all(input_receiver, output_sender, 3);
+
+    assert_eq!(output.try_iter().collect::<Vec<_>>(), vec! [0, 2, 4]);
+}
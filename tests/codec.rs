@@ -0,0 +1,97 @@
+extern crate tcp_channel;
+
+#[macro_use]
+mod common;
+
+use std::io::Cursor;
+
+use tcp_channel::{BigEndian, ChannelRecv, Codec, Endian, IntEncoding, ReceiverBuilder, RecvError, SenderBuilder, TrailingBytes};
+
+fn encode(int_encoding: IntEncoding, handshake: bool) -> Vec<u8> {
+    let builder = SenderBuilder::buffered()
+        .with_type::<u64>()
+        .with_int_encoding(int_encoding);
+    encode_with!(builder, handshake, [300])
+}
+
+#[test]
+fn default_codec() {
+    // The default codec keeps the encoding of `bincode::config()`.
+    assert_eq!(BigEndian::config(), Codec::default());
+    assert_eq!(BigEndian::config().serialize(&300u64).unwrap(), [0, 0, 0, 0, 0, 0, 1, 44]);
+    assert_eq!(encode(IntEncoding::Fixint, false)[8..], [0, 0, 0, 0, 0, 0, 1, 44]);
+}
+
+#[test]
+fn varint_integers() {
+    let bytes = encode(IntEncoding::Varint, false);
+    assert_eq!(bytes[..8], [0, 0, 0, 0, 0, 0, 0, 3]);
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<u64>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_int_encoding(IntEncoding::Varint)
+        .build(Cursor::new(bytes));
+    assert_eq!(receiver.recv().unwrap(), 300);
+}
+
+#[test]
+fn negotiated_int_encoding() {
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<u64>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_handshake()
+        .build(Cursor::new(encode(IntEncoding::Varint, true)));
+    assert_eq!(receiver.recv().unwrap(), 300);
+}
+
+#[test]
+fn trailing_bytes() {
+    // A `u64` frame received as a `u32` leaves four bytes over.
+    let receive = |trailing_bytes| {
+        ReceiverBuilder::buffered()
+            .with_type::<u32>()
+            .with_reader::<Cursor<Vec<u8>>>()
+            .with_codec(Codec::default().with_trailing_bytes(trailing_bytes))
+            .build(Cursor::new(encode(IntEncoding::Fixint, false)))
+            .recv()
+    };
+    assert_eq!(receive(TrailingBytes::Allow).unwrap(), 0);
+    match receive(TrailingBytes::Reject) {
+        Err(RecvError::BincodeError(..)) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn nested_length_limit() {
+    // A frame of 8 bytes, containing a string claiming to be a million bytes long.
+    let mut bytes = vec![0, 0, 0, 0, 0, 0, 0, 8];
+    bytes.extend_from_slice(&1_000_000u64.to_be_bytes());
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<String>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_max_size(64)
+        .build(Cursor::new(bytes));
+    match receiver.recv() {
+        Err(RecvError::BincodeError(..)) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn codec_limit() {
+    let codec = Codec::default().with_limit(Some(4));
+    assert!(codec.serialize(&300u64).is_err());
+    assert!(codec.serialize(&300u32).is_ok());
+}
+
+#[test]
+fn serialized_size() {
+    let value = (300u64, String::from("hello"), vec! [1u16, 2, 3]);
+    for codec in [Codec::default(), Codec::default().with_int_encoding(IntEncoding::Varint)] {
+        assert_eq!(codec.serialized_size(&value).unwrap(), codec.serialize(&value).unwrap().len() as u64);
+    }
+    assert!(Codec::default().with_limit(Some(4)).serialized_size(&300u64).is_err());
+}
@@ -0,0 +1,25 @@
+//! Helpers shared by the integration tests.
+
+// Each test uses only some of them.
+#![allow(dead_code, unused_macros)]
+
+use serde::Serialize;
+
+use tcp_channel::SenderBuilder;
+
+/// Encode `values` in memory with a sender from `builder`, which must have its type but no
+/// writer, adding a handshake if `handshake` is true.
+macro_rules! encode_with {
+    ($builder:expr, $handshake:expr, $values:expr) => {{
+        let builder = $builder;
+        let builder = if $handshake { builder.with_handshake() } else { builder };
+        let mut bytes = Vec::new();
+        builder.with_writer::<&mut Vec<u8>>().build(&mut bytes).send_all($values).unwrap();
+        bytes
+    }};
+}
+
+/// Encode `values` in memory with the default options.
+pub fn encode<T: Serialize>(values: &[T]) -> Vec<u8> {
+    encode_with!(SenderBuilder::buffered().with_type::<T>(), false, values)
+}
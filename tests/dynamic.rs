@@ -0,0 +1,42 @@
+extern crate tcp_channel;
+
+use std::collections::VecDeque;
+use std::io::{BufReader, BufWriter};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
+
+use tcp_channel::{ChannelError, ChannelRecv, ChannelSend, DynReceiver, DynSender, ReceiverBuilder, SenderBuilder};
+
+#[test]
+fn heterogeneous() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let outgoing = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (incoming, _) = listener.accept().unwrap();
+
+    let (std_sender, std_receiver) = mpsc::channel();
+
+    let mut senders: Vec<DynSender<String>> = vec! [
+        DynSender::new(SenderBuilder::buffered().with_type::<String>().build(BufWriter::new(outgoing))),
+        DynSender::new(std_sender),
+        DynSender::new(VecDeque::new()),
+    ];
+    for (index, sender) in senders.iter_mut().enumerate() {
+        sender.send(&format!("Hello from #{}", index)).unwrap();
+        sender.flush().unwrap();
+    }
+
+    let mut receivers: Vec<DynReceiver<String>> = vec! [
+        DynReceiver::new(ReceiverBuilder::buffered().with_type::<String>().build(BufReader::new(incoming))),
+        DynReceiver::new(std_receiver),
+    ];
+    for (index, receiver) in receivers.iter_mut().enumerate() {
+        assert_eq!(receiver.recv().unwrap(), format!("Hello from #{}", index));
+    }
+
+    // Disconnect the std channel; the error type is the same for every transport.
+    senders.remove(1);
+    match receivers[1].recv() {
+        Err(ChannelError::Disconnected) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
@@ -0,0 +1,61 @@
+extern crate tcp_channel;
+
+#[macro_use]
+mod common;
+
+use std::io::Cursor;
+
+use tcp_channel::{ByteOrderChoice, ChannelRecv, LittleEndian, NativeEndian, ReceiverBuilder, SenderBuilder};
+
+fn encode(byte_order: ByteOrderChoice, handshake: bool) -> Vec<u8> {
+    let builder = SenderBuilder::buffered()
+        .with_type::<u32>()
+        .with_byte_order(byte_order);
+    encode_with!(builder, handshake, [0x1234_5678])
+}
+
+#[test]
+fn native_endian() {
+    let mut bytes = Vec::new();
+    SenderBuilder::buffered()
+        .with_type::<u32>()
+        .with_endianness::<NativeEndian>()
+        .with_writer::<&mut Vec<u8>>()
+        .build(&mut bytes)
+        .send_all([42])
+        .unwrap();
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<u32>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_byte_order(ByteOrderChoice::native())
+        .build(Cursor::new(bytes));
+    assert_eq!(receiver.recv().unwrap(), 42);
+}
+
+#[test]
+fn runtime_byte_order() {
+    let byte_order = "little".parse::<ByteOrderChoice>().unwrap();
+    let bytes = encode(byte_order, false);
+    assert_eq!(&bytes[..8], &[4, 0, 0, 0, 0, 0, 0, 0]);
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<u32>()
+        .with_endianness::<LittleEndian>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .build(Cursor::new(bytes));
+    assert_eq!(receiver.recv().unwrap(), 0x1234_5678);
+
+    assert!("middle".parse::<ByteOrderChoice>().is_err());
+}
+
+#[test]
+fn negotiated_byte_order() {
+    // The receiver defaults to big endian, but adopts the endianness from the handshake.
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<u32>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_handshake()
+        .build(Cursor::new(encode(ByteOrderChoice::LittleEndian, true)));
+    assert_eq!(receiver.recv().unwrap(), 0x1234_5678);
+}
@@ -0,0 +1,429 @@
+// The checks shared by the fuzz targets in `fuzz/` and the corpus runner in `tests/fuzz/main.rs`. The
+// crate including this module must also declare `#[macro_use] extern crate serde_derive;`, and
+// gets `TrackingAllocator` as its global allocator.
+//
+// Inputs start with two bytes choosing the configuration, followed by either a raw stream for
+// `recv_bytes`, or a description of the frames to send for `recv_frames`.
+
+#![allow(dead_code)]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fmt::Debug;
+use std::io::{ErrorKind as IoErrorKind, Read, Result as IoResult};
+use std::rc::Rc;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use tcp_channel::testing::{Faults, FaultyStream};
+use tcp_channel::{BigEndian, ChannelRecv, ChannelSend, Codec, Endian, HeaderEncoding, IntEncoding, LittleEndian, Receiver, ReceiverBuilder, RecoveryMode, RecvError, SenderBuilder, SendError};
+
+const MAX_SIZES: [usize; 4] = [4, 16, 64, 4096];
+const HEADER_ENCODINGS: [HeaderEncoding; 4] = [HeaderEncoding::U16, HeaderEncoding::U32, HeaderEncoding::U64, HeaderEncoding::Varint];
+
+// The handshake is a 7 byte prefix, ending with the length of the rest as a big-endian `u16`.
+const HANDSHAKE_PREFIX_LENGTH: usize = 7;
+// Serde reserves space for sequences up front based on their claimed length, but never more
+// than this.
+const SERDE_PREALLOCATION: usize = 1024 * 1024;
+
+/// Records the largest allocation made by each thread, so that inputs can be checked without
+/// interference from tests running in parallel.
+pub struct TrackingAllocator;
+
+thread_local! {
+    static LARGEST_ALLOCATION: Cell<usize> = const { Cell::new(0) };
+}
+
+fn record_allocation(size: usize) {
+    let _ = LARGEST_ALLOCATION.try_with(|largest| largest.set(largest.get().max(size)));
+}
+fn take_largest_allocation() -> usize {
+    LARGEST_ALLOCATION.with(|largest| largest.replace(0))
+}
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record_allocation(layout.size());
+        System.alloc(layout)
+    }
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record_allocation(layout.size());
+        System.alloc_zeroed(layout)
+    }
+    unsafe fn realloc(&self, pointer: *mut u8, layout: Layout, size: usize) -> *mut u8 {
+        record_allocation(size);
+        System.realloc(pointer, layout, size)
+    }
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        System.dealloc(pointer, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+/// A message type to fuzz the receiver with.
+pub trait Message: Serialize + DeserializeOwned + Debug + PartialEq {
+    /// Whether the type contains sequences, for which serde reserves space up front.
+    const SEQUENCES: bool;
+    /// Build a message out of arbitrary bytes.
+    fn from_bytes(bytes: &[u8]) -> Self;
+}
+impl Message for u32 {
+    const SEQUENCES: bool = false;
+    fn from_bytes(bytes: &[u8]) -> Self {
+        bytes.iter().fold(0, |value, &byte| value.rotate_left(8) ^ u32::from(byte))
+    }
+}
+impl Message for String {
+    const SEQUENCES: bool = false;
+    fn from_bytes(bytes: &[u8]) -> Self {
+        String::from_utf8_lossy(bytes).into_owned()
+    }
+}
+impl Message for Vec<u64> {
+    const SEQUENCES: bool = true;
+    fn from_bytes(bytes: &[u8]) -> Self {
+        bytes.chunks(8).map(|chunk| chunk.iter().fold(0, |value, &byte| value << 8 | u64::from(byte))).collect()
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    id: u64,
+    name: Option<String>,
+    kind: Kind,
+}
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub enum Kind {
+    Empty,
+    Pair(i16, i16),
+    Bytes(Vec<u8>),
+}
+impl Message for Record {
+    const SEQUENCES: bool = true;
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let (&first, rest) = match bytes.split_first() {
+            Some(split) => split,
+            None => return Record { id: 0, name: None, kind: Kind::Empty },
+        };
+        Record {
+            id: u32::from_bytes(rest).into(),
+            name: if first & 1 == 0 { None } else { Some(String::from_bytes(rest)) },
+            kind: match first >> 1 & 3 {
+                0 => Kind::Empty,
+                1 => Kind::Pair(first.into(), -i16::from(first)),
+                _ => Kind::Bytes(rest.to_vec()),
+            },
+        }
+    }
+}
+
+/// The configuration of a channel, taken from the first two bytes of an input.
+#[derive(Clone, Copy, Debug)]
+pub struct Config {
+    message: u8,
+    little_endian: bool,
+    header: HeaderEncoding,
+    int_encoding: IntEncoding,
+    recovery: RecoveryMode,
+    handshake: bool,
+    max_size: usize,
+    seed: u64,
+}
+impl Config {
+    pub fn parse(data: &[u8]) -> Option<(Self, &[u8])> {
+        if data.len() < 2 {
+            return None
+        }
+        let (flags, extra) = (data[0], data[1]);
+        let config = Config {
+            message: flags & 3,
+            little_endian: flags & 4 != 0,
+            header: HEADER_ENCODINGS[(flags >> 3 & 3) as usize],
+            int_encoding: if flags & 0x20 == 0 { IntEncoding::Fixint } else { IntEncoding::Varint },
+            recovery: if flags & 0x40 == 0 { RecoveryMode::Poison } else { RecoveryMode::SkipFrame },
+            handshake: flags & 0x80 != 0,
+            max_size: MAX_SIZES[(extra & 3) as usize],
+            seed: (extra >> 2).into(),
+        };
+        Some((config, &data[2..]))
+    }
+    // The most the receive buffer may grow to.
+    fn buffer_limit(&self) -> usize {
+        if self.handshake {
+            self.max_size.max(u16::MAX.into())
+        } else {
+            self.max_size
+        }
+    }
+    // The largest allocation receiving a `T` may make. Small allocations, such as the error
+    // types, are always allowed.
+    fn allocation_limit<T: Message>(&self) -> usize {
+        let limit = self.buffer_limit().max(1024);
+        if T::SEQUENCES {
+            limit.max(SERDE_PREALLOCATION)
+        } else {
+            limit
+        }
+    }
+    fn dispatch<R: Run>(self, run: R) {
+        match (self.message, self.little_endian) {
+            (0, false) => run.run::<u32, BigEndian>(self),
+            (0, true) => run.run::<u32, LittleEndian>(self),
+            (1, false) => run.run::<String, BigEndian>(self),
+            (1, true) => run.run::<String, LittleEndian>(self),
+            (2, false) => run.run::<Vec<u64>, BigEndian>(self),
+            (2, true) => run.run::<Vec<u64>, LittleEndian>(self),
+            (_, false) => run.run::<Record, BigEndian>(self),
+            (_, true) => run.run::<Record, LittleEndian>(self),
+        }
+    }
+}
+
+// A check, which is generic over the message type and the endianness.
+trait Run {
+    fn run<T: Message, E: Endian>(self, config: Config);
+}
+
+// A reader counting the bytes read from it.
+struct Counting<R> {
+    inner: R,
+    count: Rc<Cell<u64>>,
+}
+impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> IoResult<usize> {
+        let size = self.inner.read(buffer)?;
+        self.count.set(self.count.get() + size as u64);
+        Ok(size)
+    }
+}
+
+/// Feed an arbitrary stream to a receiver.
+///
+/// Checks that the receiver never allocates much more than the max size, that frames always end
+/// where the headers say they do, and that it stops at the first error after which it can't
+/// know where the next frame begins.
+pub fn recv_bytes(data: &[u8]) {
+    if let Some((config, stream)) = Config::parse(data) {
+        config.dispatch(RecvBytes(stream));
+    }
+}
+
+struct RecvBytes<'a>(&'a [u8]);
+impl<'a> Run for RecvBytes<'a> {
+    fn run<T: Message, E: Endian>(self, config: Config) {
+        let stream = self.0;
+        let count = Rc::new(Cell::new(0));
+        let mut builder = ReceiverBuilder::buffered()
+            .with_type::<T>()
+            .with_reader::<Counting<&[u8]>>()
+            .with_endianness::<E>()
+            .with_max_size(config.max_size)
+            .with_header_encoding(config.header)
+            .with_int_encoding(config.int_encoding)
+            .with_recovery(config.recovery);
+        if config.handshake {
+            builder = builder.with_handshake();
+        }
+        let mut receiver = builder.build(Counting { inner: stream, count: count.clone() });
+
+        // Where frames begin, if the handshake is valid.
+        let handshake_length = if config.handshake && stream.len() >= HANDSHAKE_PREFIX_LENGTH {
+            HANDSHAKE_PREFIX_LENGTH as u64 + u64::from(u16::from_be_bytes([stream[5], stream[6]]))
+        } else {
+            0
+        };
+        let mut skipped = 0;
+        let expected_offset = |receiver: &Receiver<T, E, Counting<&[u8]>>, skipped: u64| {
+            let stats = receiver.stats();
+            handshake_length + stats.header_bytes + stats.payload_bytes + skipped
+        };
+
+        take_largest_allocation();
+        loop {
+            let offset = count.get();
+            let result = receiver.recv();
+            assert!(receiver.buffer_capacity() <= config.buffer_limit(), "buffer grew to {} bytes", receiver.buffer_capacity());
+
+            match result {
+                Ok(_) | Err(RecvError::BincodeError(..)) => {
+                    assert!(count.get() > offset, "received a frame without reading anything");
+                    assert_eq!(count.get(), expected_offset(&receiver, skipped));
+                },
+                Err(RecvError::TooLarge(length)) => {
+                    assert!(length > config.max_size);
+                    if config.recovery == RecoveryMode::Poison {
+                        assert_poisoned(&mut receiver);
+                        break
+                    }
+                    // Everything read so far that isn't in the stats, which includes the header of
+                    // this frame, is skipped, and so is its payload.
+                    skipped = (count.get() - expected_offset(&receiver, 0)).saturating_add(length as u64);
+                },
+                Err(RecvError::Disconnected) => {
+                    assert_eq!(count.get(), stream.len() as u64);
+                    assert_eq!(count.get(), expected_offset(&receiver, skipped));
+                    break
+                },
+                Err(RecvError::InvalidHeader) | Err(RecvError::InvalidHandshake) | Err(RecvError::MaxSizeMismatch(..)) => {
+                    assert_poisoned(&mut receiver);
+                    break
+                },
+                Err(RecvError::IoError(ref error)) if error.kind() == IoErrorKind::UnexpectedEof => {
+                    assert_eq!(count.get(), stream.len() as u64);
+                    assert_poisoned(&mut receiver);
+                    break
+                },
+                Err(error) => panic!("unexpected error: {:?}", error),
+            }
+        }
+
+        let largest = take_largest_allocation();
+        assert!(largest <= config.allocation_limit::<T>(), "allocated {} bytes at once", largest);
+    }
+}
+
+fn assert_poisoned<T: Message, E: Endian, R: Read>(receiver: &mut Receiver<T, E, R>) {
+    match receiver.recv() {
+        Err(RecvError::Poisoned) => (),
+        result => panic!("expected a poisoned receiver, got {:?}", result),
+    }
+}
+
+/// Send frames described by the input through a sender, and back through a receiver reading
+/// from a stream with short reads and spurious errors.
+///
+/// The rest of the input is a sequence of frames, each made of a byte choosing the kind of frame,
+/// a byte with the length of its contents, and its contents. Valid messages must be received
+/// intact, whatever garbage frames came before them.
+pub fn recv_frames(data: &[u8]) {
+    if let Some((config, frames)) = Config::parse(data) {
+        config.dispatch(RecvFrames(frames));
+    }
+}
+
+/// Turn an input for `recv_frames` into one for `recv_bytes`, with the same configuration and
+/// the stream the frames are encoded into.
+pub fn encode_frames(data: &[u8]) -> Vec<u8> {
+    let mut encoded = data[..data.len().min(2)].to_vec();
+    if let Some((config, frames)) = Config::parse(data) {
+        config.dispatch(EncodeFrames(frames, &mut encoded));
+    }
+    encoded
+}
+
+struct EncodeFrames<'a>(&'a [u8], &'a mut Vec<u8>);
+impl<'a> Run for EncodeFrames<'a> {
+    fn run<T: Message, E: Endian>(self, config: Config) {
+        let (stream, _) = send_frames::<T, E>(config, self.0);
+        self.1.extend_from_slice(&stream);
+    }
+}
+
+// What the receiver should return for a frame.
+enum Expected<T> {
+    Message(T),
+    // Random bytes, which may or may not deserialize.
+    Garbage,
+    TooLarge(usize),
+}
+
+struct RecvFrames<'a>(&'a [u8]);
+impl<'a> Run for RecvFrames<'a> {
+    fn run<T: Message, E: Endian>(self, config: Config) {
+        let (stream, expected) = send_frames::<T, E>(config, self.0);
+
+        let faults = Faults::new(config.seed).with_short_io(0.5).with_would_block(0.1).with_interrupted(0.1);
+        let mut builder = ReceiverBuilder::buffered()
+            .with_type::<T>()
+            .with_reader::<FaultyStream<&[u8]>>()
+            .with_endianness::<E>()
+            .with_max_size(config.max_size)
+            .with_header_encoding(config.header)
+            .with_int_encoding(config.int_encoding)
+            .with_recovery(config.recovery);
+        if config.handshake {
+            builder = builder.with_handshake();
+        }
+        let mut receiver = builder.build(FaultyStream::new(&stream[..], faults));
+
+        take_largest_allocation();
+        for expected in expected {
+            match (expected, recv_blocking(&mut receiver)) {
+                (Expected::Message(value), Ok(received)) => assert_eq!(value, received),
+                (Expected::Garbage, Ok(_)) | (Expected::Garbage, Err(RecvError::BincodeError(..))) => (),
+                (Expected::TooLarge(length), Err(RecvError::TooLarge(received))) => {
+                    assert_eq!(length, received);
+                    if config.recovery == RecoveryMode::Poison {
+                        assert!(matches!(recv_blocking(&mut receiver), Err(RecvError::Poisoned)));
+                        return
+                    }
+                },
+                (_, result) => panic!("unexpected result: {:?}", result),
+            }
+            assert!(receiver.buffer_capacity() <= config.buffer_limit());
+        }
+        assert!(matches!(recv_blocking(&mut receiver), Err(RecvError::Disconnected)));
+
+        let largest = take_largest_allocation();
+        assert!(largest <= config.allocation_limit::<T>(), "allocated {} bytes at once", largest);
+    }
+}
+
+fn recv_blocking<T: Message, E: Endian, R: Read>(receiver: &mut Receiver<T, E, R>) -> Result<T, RecvError> {
+    loop {
+        match receiver.recv() {
+            Err(RecvError::IoError(ref error)) if error.kind() == IoErrorKind::WouldBlock => (),
+            result => return result,
+        }
+    }
+}
+
+// Encode the frames described by `data`, returning the stream and what receiving each frame
+// should return. Without the handshake, which would announce it, the sender is allowed larger
+// frames than the receiver.
+fn send_frames<T: Message, E: Endian>(config: Config, mut data: &[u8]) -> (Vec<u8>, Vec<Expected<T>>) {
+    let sender_max_size = if config.handshake { config.max_size } else { config.max_size * 4 };
+    let codec = Codec::new(E::byte_order()).with_int_encoding(config.int_encoding);
+
+    let mut stream = Vec::new();
+    let mut expected = Vec::new();
+    {
+        let mut builder = SenderBuilder::buffered()
+            .with_type::<T>()
+            .with_writer::<&mut Vec<u8>>()
+            .with_endianness::<E>()
+            .with_max_size(sender_max_size)
+            .with_header_encoding(config.header)
+            .with_int_encoding(config.int_encoding);
+        if config.handshake {
+            builder = builder.with_handshake();
+        }
+        let mut sender = builder.build(&mut stream);
+
+        while data.len() >= 2 {
+            let (kind, length) = (data[0], data[1] as usize);
+            let contents = &data[2..data.len().min(2 + length)];
+            data = &data[2 + contents.len()..];
+
+            let (payload_length, result, frame) = if kind & 1 == 0 {
+                let value = T::from_bytes(contents);
+                let payload_length = codec.serialize(&value).unwrap().len();
+                (payload_length, sender.send(&value), Expected::Message(value))
+            } else {
+                (contents.len(), sender.send_raw(contents), Expected::Garbage)
+            };
+            match result {
+                Ok(()) if payload_length > config.max_size => expected.push(Expected::TooLarge(payload_length)),
+                Ok(()) => expected.push(frame),
+                Err(SendError::TooLarge(_)) => (),
+                Err(error) => panic!("failed to send: {:?}", error),
+            }
+        }
+        sender.flush().unwrap();
+    }
+    (stream, expected)
+}
@@ -0,0 +1,96 @@
+// Runs the checks of the fuzz targets on a deterministic set of inputs, along with the corpus in
+// `fuzz/corpus` if there is one, so that they are exercised without cargo-fuzz.
+
+extern crate rand;
+extern crate serde;
+#[macro_use] extern crate serde_derive;
+extern crate tcp_channel;
+
+mod harness;
+
+use std::fs;
+use std::path::Path;
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+const INPUTS: u64 = 2000;
+
+// Random inputs, up to 512 bytes long.
+fn random_input(rng: &mut StdRng) -> Vec<u8> {
+    let length = rng.gen_range(0, 512);
+    (0..length).map(|_| rng.gen()).collect()
+}
+
+// Run `check` on every file in the corpus of `target`.
+fn run_corpus(target: &str, check: fn(&[u8])) {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/corpus").join(target);
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries {
+        let path = entry.unwrap().path();
+        let input = fs::read(&path).unwrap();
+        println!("{}", path.display());
+        check(&input);
+    }
+}
+
+#[test]
+fn recv_bytes_random() {
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..INPUTS {
+        harness::recv_bytes(&random_input(&mut rng));
+    }
+}
+
+#[test]
+fn recv_bytes_corrupted() {
+    // Valid streams, with a few bytes flipped, removed or inserted.
+    let mut rng = StdRng::seed_from_u64(1);
+    for _ in 0..INPUTS {
+        let mut input = harness::encode_frames(&random_input(&mut rng));
+        for _ in 0..rng.gen_range(0, 4) {
+            if input.len() <= 2 {
+                break
+            }
+            let index = rng.gen_range(2, input.len());
+            match rng.gen_range(0, 3) {
+                0 => input[index] ^= 1 << rng.gen_range(0, 8),
+                1 => { input.remove(index); },
+                _ => input.insert(index, rng.gen()),
+            }
+        }
+        harness::recv_bytes(&input);
+    }
+}
+
+#[test]
+fn recv_bytes_valid() {
+    let mut rng = StdRng::seed_from_u64(2);
+    for _ in 0..INPUTS {
+        harness::recv_bytes(&harness::encode_frames(&random_input(&mut rng)));
+    }
+}
+
+#[test]
+fn recv_frames_random() {
+    let mut rng = StdRng::seed_from_u64(3);
+    for _ in 0..INPUTS {
+        harness::recv_frames(&random_input(&mut rng));
+    }
+}
+
+#[test]
+fn regressions() {
+    // A header claiming `u64::MAX` bytes, with the frame skipped.
+    let mut input = vec! [0x74, 0xFF];
+    input.extend_from_slice(&[0xFF; 38]);
+    harness::recv_bytes(&input);
+}
+
+#[test]
+fn corpus() {
+    run_corpus("recv_bytes", harness::recv_bytes);
+    run_corpus("recv_frames", harness::recv_frames);
+}
@@ -0,0 +1,107 @@
+extern crate tcp_channel;
+
+#[macro_use]
+mod common;
+
+use std::io::Cursor;
+
+use tcp_channel::{ChannelRecv, ChannelSend, ReceiverBuilder, RecvError, SendError, SenderBuilder};
+
+fn encode(max_size: usize, handshake: bool, values: &[Vec<u8>]) -> Vec<u8> {
+    let builder = SenderBuilder::buffered()
+        .with_type::<Vec<u8>>()
+        .with_max_size(max_size);
+    encode_with!(builder, handshake, values)
+}
+
+#[test]
+fn too_large_to_send() {
+    let mut bytes = Vec::new();
+    let mut sender = SenderBuilder::buffered()
+        .with_type::<Vec<u8>>()
+        .with_writer::<&mut Vec<u8>>()
+        .with_max_size(1024)
+        .build(&mut bytes);
+
+    match sender.send(&vec! [0; 2048]) {
+        Err(SendError::TooLarge(size)) => assert!(size > 2048),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    // A rejected message doesn't affect the next one.
+    sender.send(&vec! [0; 512]).unwrap();
+    drop(sender);
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<Vec<u8>>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .build(Cursor::new(bytes));
+    assert_eq!(receiver.recv().unwrap().len(), 512);
+    assert!(receiver.recv().is_err());
+}
+
+#[test]
+fn handshake() {
+    let bytes = encode(4096, true, &[vec! [1; 10], vec! [2; 20]]);
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<Vec<u8>>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_max_size(4096)
+        .with_handshake()
+        .build(Cursor::new(bytes));
+
+    assert_eq!(receiver.recv().unwrap(), vec! [1; 10]);
+    assert_eq!(receiver.recv().unwrap(), vec! [2; 20]);
+}
+
+#[test]
+fn max_size_mismatch() {
+    let bytes = encode(8192, true, &[vec! [1; 10]]);
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<Vec<u8>>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_max_size(4096)
+        .with_handshake()
+        .build(Cursor::new(bytes));
+
+    match receiver.recv() {
+        Err(RecvError::MaxSizeMismatch(8192, 4096)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn missing_handshake() {
+    let bytes = encode(4096, false, &[vec! [1; 10]]);
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<Vec<u8>>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_handshake()
+        .build(Cursor::new(bytes));
+
+    match receiver.recv() {
+        Err(RecvError::InvalidHandshake) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn nothing_sent() {
+    // The handshake goes along with the first message, so a sender that sends nothing doesn't
+    // send it either.
+    let bytes = encode(4096, true, &[]);
+    assert!(bytes.is_empty());
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<Vec<u8>>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_handshake()
+        .build(Cursor::new(bytes));
+
+    match receiver.recv() {
+        Err(RecvError::Disconnected) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
@@ -0,0 +1,113 @@
+extern crate tcp_channel;
+
+#[macro_use]
+mod common;
+
+use std::io::{Cursor, ErrorKind as IoErrorKind, Read, Result as IoResult};
+
+use tcp_channel::{ChannelRecv, ChannelSend, HeaderEncoding, LittleEndian, ReceiverBuilder, RecvError, SendError, SenderBuilder};
+
+const ENCODINGS: [HeaderEncoding; 4] = [HeaderEncoding::U16, HeaderEncoding::U32, HeaderEncoding::U64, HeaderEncoding::Varint];
+
+// Reads a single byte at a time, returning `WouldBlock` before every byte.
+struct Trickle {
+    inner: Cursor<Vec<u8>>,
+    blocked: bool,
+}
+impl Read for Trickle {
+    fn read(&mut self, buffer: &mut [u8]) -> IoResult<usize> {
+        self.blocked = !self.blocked;
+        if self.blocked {
+            return Err(IoErrorKind::WouldBlock.into())
+        }
+        let length = buffer.len().min(1);
+        self.inner.read(&mut buffer[..length])
+    }
+}
+
+fn messages() -> Vec<Vec<u8>> {
+    [0, 1, 6, 127, 128, 300, 16_383, 16_384, 65_000].iter().map(|&size| vec! [0xAA; size]).collect()
+}
+
+fn encode(header: HeaderEncoding, handshake: bool) -> Vec<u8> {
+    let builder = SenderBuilder::buffered()
+        .with_type::<Vec<u8>>()
+        .with_endianness::<LittleEndian>()
+        .with_header_encoding(header);
+    encode_with!(builder, handshake, messages())
+}
+
+#[test]
+fn roundtrip() {
+    for &header in &ENCODINGS {
+        let mut receiver = ReceiverBuilder::buffered()
+            .with_type::<Vec<u8>>()
+            .with_endianness::<LittleEndian>()
+            .with_reader::<Trickle>()
+            .with_header_encoding(header)
+            .build(Trickle { inner: Cursor::new(encode(header, false)), blocked: false });
+
+        let mut received = Vec::new();
+        loop {
+            match receiver.recv() {
+                Ok(message) => received.push(message),
+                Err(RecvError::IoError(ref error)) if error.kind() == IoErrorKind::WouldBlock => continue,
+                Err(RecvError::Disconnected) => break,
+                Err(error) => panic!("unexpected error with {:?}: {:?}", header, error),
+            }
+        }
+        assert_eq!(received, messages(), "{:?}", header);
+    }
+}
+
+#[test]
+fn varint_is_shorter() {
+    let varint = encode(HeaderEncoding::Varint, false).len();
+    let fixed = encode(HeaderEncoding::U64, false).len();
+    assert!(varint < fixed);
+}
+
+#[test]
+fn handshake_announces_encoding() {
+    for &header in &ENCODINGS {
+        // The receiver uses the default encoding, but adopts the one from the handshake.
+        let mut receiver = ReceiverBuilder::buffered()
+            .with_type::<Vec<u8>>()
+            .with_endianness::<LittleEndian>()
+            .with_reader::<Cursor<Vec<u8>>>()
+            .with_handshake()
+            .build(Cursor::new(encode(header, true)));
+
+        let received = receiver.iter().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(received, messages(), "{:?}", header);
+    }
+}
+
+#[test]
+fn too_large_for_header() {
+    let mut bytes = Vec::new();
+    let mut sender = SenderBuilder::buffered()
+        .with_type::<Vec<u8>>()
+        .with_writer::<&mut Vec<u8>>()
+        .with_header_encoding(HeaderEncoding::U16)
+        .build(&mut bytes);
+
+    match sender.send(&vec! [0; 70_000]) {
+        Err(SendError::TooLarge(_)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn invalid_varint() {
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<Vec<u8>>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_header_encoding(HeaderEncoding::Varint)
+        .build(Cursor::new(vec! [0xFF; 11]));
+
+    match receiver.recv() {
+        Err(RecvError::InvalidHeader) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
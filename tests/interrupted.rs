@@ -0,0 +1,186 @@
+extern crate tcp_channel;
+
+mod common;
+
+use std::io::{Cursor, ErrorKind as IoErrorKind, Read, Result as IoResult};
+
+use tcp_channel::testing::{Faults, FaultyStream};
+use tcp_channel::{ChannelRecv, ChannelSend, ReceiverBuilder, RecvError, SendError, SenderBuilder};
+
+use common::encode;
+
+fn is_transient(kind: IoErrorKind) -> bool {
+    kind == IoErrorKind::WouldBlock || kind == IoErrorKind::TimedOut
+}
+
+// A reader that times out before every read of at most 3 bytes, like a socket with a read
+// timeout receiving data slowly.
+struct SlowReader<R> {
+    inner: R,
+    timed_out: bool,
+}
+impl<R: Read> Read for SlowReader<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> IoResult<usize> {
+        self.timed_out = !self.timed_out;
+        if self.timed_out {
+            return Err(IoErrorKind::TimedOut.into())
+        }
+        let length = buffer.len().min(3);
+        self.inner.read(&mut buffer[..length])
+    }
+}
+
+#[test]
+fn interrupted_is_retried() {
+    let values = (0..100).collect::<Vec<u64>>();
+    let faults = Faults::new(7).with_short_io(0.5).with_interrupted(0.5);
+
+    let mut writer = FaultyStream::new(Vec::new(), faults);
+    SenderBuilder::realtime()
+        .with_type::<u64>()
+        .with_writer::<&mut FaultyStream<Vec<u8>>>()
+        .build(&mut writer)
+        .send_all(&values)
+        .unwrap();
+
+    let mut receiver = ReceiverBuilder::realtime()
+        .with_type::<u64>()
+        .with_reader::<FaultyStream<Cursor<Vec<u8>>>>()
+        .build(FaultyStream::new(Cursor::new(writer.into_inner()), faults));
+    let received = receiver.iter().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(received, values);
+}
+
+#[test]
+fn resume_after_would_block() {
+    let values = (0..100).map(|value| vec! [value; value as usize]).collect::<Vec<Vec<u8>>>();
+    let faults = Faults::new(3).with_short_io(0.5).with_would_block(0.3);
+
+    // Every message is sent once; a message whose write would block is finished by the next call.
+    let mut writer = FaultyStream::new(Vec::new(), faults);
+    {
+        let mut sender = SenderBuilder::realtime()
+            .with_type::<Vec<u8>>()
+            .with_writer::<&mut FaultyStream<Vec<u8>>>()
+            .with_handshake()
+            .build(&mut writer);
+        let mut blocked = 0;
+        for value in &values {
+            match sender.send(value) {
+                Ok(()) => (),
+                Err(SendError::IoError(ref error)) if is_transient(error.kind()) => blocked += 1,
+                Err(error) => panic!("unexpected error: {:?}", error),
+            }
+        }
+        loop {
+            match sender.flush() {
+                Ok(()) => break,
+                Err(ref error) if is_transient(error.kind()) => blocked += 1,
+                Err(error) => panic!("unexpected error: {:?}", error),
+            }
+        }
+        assert!(blocked > 0);
+        assert_eq!(sender.stats().messages, values.len() as u64);
+    }
+
+    let mut receiver = ReceiverBuilder::realtime()
+        .with_type::<Vec<u8>>()
+        .with_reader::<FaultyStream<Cursor<Vec<u8>>>>()
+        .with_handshake()
+        .build(FaultyStream::new(Cursor::new(writer.into_inner()), faults));
+    let mut received = Vec::new();
+    loop {
+        match receiver.recv() {
+            Ok(value) => received.push(value),
+            Err(RecvError::Disconnected) => break,
+            Err(RecvError::IoError(ref error)) if is_transient(error.kind()) => (),
+            Err(error) => panic!("unexpected error: {:?}", error),
+        }
+    }
+    assert_eq!(received, values);
+}
+
+#[test]
+fn resume_after_timed_out() {
+    let values = (0..20).collect::<Vec<u32>>();
+    let mut receiver = ReceiverBuilder::realtime()
+        .with_type::<u32>()
+        .with_reader::<SlowReader<Cursor<Vec<u8>>>>()
+        .build(SlowReader { inner: Cursor::new(encode(&values)), timed_out: false });
+
+    let mut received = Vec::new();
+    let mut timeouts = 0;
+    loop {
+        match receiver.recv() {
+            Ok(value) => received.push(value),
+            Err(RecvError::Disconnected) => break,
+            Err(RecvError::IoError(ref error)) if error.kind() == IoErrorKind::TimedOut => timeouts += 1,
+            Err(error) => panic!("unexpected error: {:?}", error),
+        }
+    }
+    assert_eq!(received, values);
+    assert!(timeouts > values.len());
+}
+
+#[test]
+fn fatal_errors_poison() {
+    // The last frame is cut short, so iterating ends instead of failing forever.
+    let mut bytes = encode::<u32>(&[1, 2]);
+    bytes.truncate(bytes.len() - 2);
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<u32>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .build(Cursor::new(bytes));
+    let results = receiver.iter().take(10).collect::<Vec<_>>();
+    assert_eq!(results.len(), 2);
+    assert_eq!(*results[0].as_ref().unwrap(), 1);
+    match results[1] {
+        Err(RecvError::IoError(ref error)) if error.kind() == IoErrorKind::UnexpectedEof => (),
+        ref result => panic!("unexpected result: {:?}", result),
+    }
+    match receiver.recv() {
+        Err(RecvError::Poisoned) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+
+    // The same goes for a connection reset in the middle of a frame.
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<u32>()
+        .with_reader::<FaultyStream<Cursor<Vec<u8>>>>()
+        .build(FaultyStream::new(Cursor::new(encode::<u32>(&[1, 2])), Faults::new(0).disconnect_at(14)));
+    assert_eq!(receiver.recv().unwrap(), 1);
+    match receiver.recv() {
+        Err(RecvError::IoError(ref error)) if error.kind() == IoErrorKind::ConnectionReset => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    match receiver.recv() {
+        Err(RecvError::Poisoned) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn fatal_write_errors_poison() {
+    // The writer takes part of the first frame and then fails, so the receiver is left in the
+    // middle of it, and nothing more may be sent.
+    let mut bytes = Vec::new();
+    {
+        let mut sender = SenderBuilder::realtime()
+            .with_type::<u32>()
+            .with_writer::<FaultyStream<&mut Vec<u8>>>()
+            .build(FaultyStream::new(&mut bytes, Faults::new(0).disconnect_at(3)));
+        match sender.send(&1) {
+            Err(SendError::IoError(ref error)) if error.kind() == IoErrorKind::BrokenPipe => (),
+            result => panic!("unexpected result: {:?}", result),
+        }
+        match sender.send(&2) {
+            Err(SendError::Poisoned) => (),
+            result => panic!("unexpected result: {:?}", result),
+        }
+        match ChannelSend::flush(&mut sender) {
+            Err(SendError::Poisoned) => (),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+    assert_eq!(bytes.len(), 3);
+}
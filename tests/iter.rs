@@ -0,0 +1,81 @@
+extern crate tcp_channel;
+
+mod common;
+
+use std::io::{Cursor, ErrorKind as IoErrorKind, Read, Result as IoResult};
+
+use tcp_channel::{BigEndian, ChannelRecv, ReceiverBuilder, RecvError, SenderBuilder};
+
+use common::encode;
+
+// A reader that returns `WouldBlock` instead of EOF once its data has been read.
+struct WouldBlockAtEnd(Cursor<Vec<u8>>);
+
+impl Read for WouldBlockAtEnd {
+    fn read(&mut self, buffer: &mut [u8]) -> IoResult<usize> {
+        match self.0.read(buffer)? {
+            0 => Err(IoErrorKind::WouldBlock.into()),
+            size => Ok(size),
+        }
+    }
+}
+
+#[test]
+fn iter_ends_on_disconnect() {
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<u32>()
+        .with_endianness::<BigEndian>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .build(Cursor::new(encode::<u32>(&[1, 2, 3])));
+
+    let values = receiver.iter().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(values, vec! [1, 2, 3]);
+
+    match receiver.recv() {
+        Err(RecvError::Disconnected) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn into_iter() {
+    let receiver = ReceiverBuilder::buffered()
+        .with_type::<u32>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .build(Cursor::new(encode::<u32>(&[4, 5])));
+
+    let mut sum = 0;
+    for value in receiver {
+        sum += value.unwrap();
+    }
+    assert_eq!(sum, 9);
+}
+
+#[test]
+fn try_iter_ends_on_would_block() {
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<u32>()
+        .with_reader::<WouldBlockAtEnd>()
+        .build(WouldBlockAtEnd(Cursor::new(encode::<u32>(&[6, 7, 8]))));
+
+    let values = receiver.try_iter().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(values, vec! [6, 7, 8]);
+}
+
+#[test]
+fn empty_messages() {
+    let mut bytes = Vec::new();
+    SenderBuilder::buffered()
+        .with_type::<()>()
+        .with_writer::<&mut Vec<u8>>()
+        .build(&mut bytes)
+        .send_all([(), ()])
+        .unwrap();
+
+    let receiver = ReceiverBuilder::buffered()
+        .with_type::<()>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .build(Cursor::new(bytes));
+
+    assert_eq!(receiver.into_iter().count(), 2);
+}
@@ -0,0 +1,42 @@
+extern crate tcp_channel;
+
+use std::io::Cursor;
+use std::net::TcpListener;
+use std::thread;
+use std::time::Duration;
+
+use tcp_channel::{ChannelRecv, ChannelSend, ReceiverBuilder, SenderBuilder};
+
+#[test]
+fn peer_addr() {
+    // Find a free port for `listen_once` to bind to.
+    let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+    let receiver = thread::spawn(move || {
+        let mut receiver = ReceiverBuilder::realtime()
+            .with_type::<u32>()
+            .listen_once(address)
+            .unwrap();
+        assert_eq!(receiver.recv().unwrap(), 42);
+        receiver.peer_addr().unwrap()
+    });
+
+    let mut sender = loop {
+        match SenderBuilder::realtime().with_type::<u32>().connect(address) {
+            Ok(sender) => break sender,
+            Err(_) => thread::sleep(Duration::from_millis(10)),
+        }
+    };
+    assert_eq!(sender.peer_addr(), Some(address));
+    sender.send(&42).unwrap();
+
+    let sender_address = receiver.join().unwrap();
+    assert_eq!(sender_address.ip(), address.ip());
+
+    // Channels built on other transports have no peer.
+    let receiver = ReceiverBuilder::buffered()
+        .with_type::<u32>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .build(Cursor::new(Vec::new()));
+    assert_eq!(receiver.peer_addr(), None);
+}
@@ -0,0 +1,54 @@
+extern crate tcp_channel;
+
+use std::io::Cursor;
+
+use tcp_channel::{BigEndian, ChannelRecv, ChannelSend, Endian, ReceiverBuilder, SenderBuilder};
+
+#[test]
+fn forward_raw_frames() {
+    let mut bytes = Vec::new();
+    SenderBuilder::buffered()
+        .with_type::<String>()
+        .with_writer::<&mut Vec<u8>>()
+        .build(&mut bytes)
+        .send_all(["first".to_string(), "second".to_string()])
+        .unwrap();
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<String>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .build(Cursor::new(bytes.clone()));
+    let mut forwarded = Vec::new();
+    {
+        let mut sender = SenderBuilder::buffered()
+            .with_type::<String>()
+            .with_writer::<&mut Vec<u8>>()
+            .build(&mut forwarded);
+        while let Ok(frame) = receiver.recv_raw() {
+            sender.send_raw(frame).unwrap();
+        }
+    }
+    assert_eq!(forwarded, bytes);
+}
+
+#[test]
+fn raw_and_typed() {
+    let mut bytes = Vec::new();
+    {
+        let mut sender = SenderBuilder::buffered()
+            .with_type::<u32>()
+            .with_writer::<&mut Vec<u8>>()
+            .build(&mut bytes);
+        sender.send_raw(b"custom").unwrap();
+        sender.send_raw(&BigEndian::config().serialize(&42u32).unwrap()).unwrap();
+        sender.send(&7).unwrap();
+    }
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<u32>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .build(Cursor::new(bytes));
+    assert_eq!(receiver.recv_raw().unwrap(), b"custom");
+    assert_eq!(receiver.recv().unwrap(), 42);
+    assert_eq!(receiver.recv_raw().unwrap(), [0, 0, 0, 7]);
+}
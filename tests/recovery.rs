@@ -0,0 +1,68 @@
+extern crate tcp_channel;
+
+mod common;
+
+use std::io::Cursor;
+
+use tcp_channel::{ChannelRecv, ReceiverBuilder, RecoveryMode, RecvError};
+
+use common::encode;
+
+#[test]
+fn skip_frame() {
+    let bytes = encode::<Vec<u8>>(&[vec! [1; 10], vec! [2; 100_000], vec! [3; 10]]);
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<Vec<u8>>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_max_size(1024)
+        .with_recovery(RecoveryMode::SkipFrame)
+        .build(Cursor::new(bytes));
+
+    assert_eq!(receiver.recv().unwrap(), vec! [1; 10]);
+    match receiver.recv() {
+        Err(RecvError::TooLarge(size)) => assert_eq!(size, 100_008),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    assert_eq!(receiver.recv().unwrap(), vec! [3; 10]);
+    assert!(receiver.buffer_capacity() < 1024);
+}
+
+#[test]
+fn poison() {
+    let bytes = encode::<Vec<u8>>(&[vec! [1; 10], vec! [2; 100_000], vec! [3; 10]]);
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<Vec<u8>>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_max_size(1024)
+        .build(Cursor::new(bytes));
+
+    assert_eq!(receiver.recv().unwrap(), vec! [1; 10]);
+    match receiver.recv() {
+        Err(RecvError::TooLarge(_)) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    match receiver.recv() {
+        Err(RecvError::Poisoned) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    // Iterating ends instead of yielding the same error forever.
+    assert_eq!(receiver.iter().count(), 0);
+}
+
+#[test]
+fn decode_error() {
+    let bytes = encode(&[vec! [0xff], b"valid".to_vec()]);
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<String>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .build(Cursor::new(bytes));
+
+    match receiver.recv() {
+        Err(RecvError::BincodeError(_, frame)) => assert_eq!(frame, [0, 0, 0, 0, 0, 0, 0, 1, 0xff]),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    assert_eq!(receiver.recv().unwrap(), "valid");
+}
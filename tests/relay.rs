@@ -0,0 +1,100 @@
+extern crate tcp_channel;
+
+use std::io::{BufReader, BufWriter};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::thread;
+
+use tcp_channel::{ChannelRecv, ChannelSend, Direction, ForwardError, Relay, ReceiverBuilder, RecvError, SenderBuilder};
+
+// Connect a client to a server through a relay running on a thread, returning the client and
+// server connections, and the thread.
+fn connect<F>(relay: Relay, run: F) -> (TcpStream, TcpStream, thread::JoinHandle<()>)
+where
+    F: FnOnce(Relay, TcpStream, TcpStream) + Send + 'static,
+{
+    let relay_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let server_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let server_address = server_listener.local_addr().unwrap();
+
+    let client = TcpStream::connect(relay_listener.local_addr().unwrap()).unwrap();
+    let (a, _) = relay_listener.accept().unwrap();
+    let b = TcpStream::connect(server_address).unwrap();
+    let (server, _) = server_listener.accept().unwrap();
+
+    let thread = thread::spawn(move || run(relay, a, b));
+    (client, server, thread)
+}
+
+#[test]
+fn relay_with_filter() {
+    let relay = Relay::new().with_filter(|direction, frame| direction == Direction::BToA || frame != [0, 0, 0, 13]);
+    let (client, server, relay) = connect(relay, |relay, a, b| {
+        let stats = relay.run(a, b).unwrap();
+        assert_eq!((stats.a_to_b.frames, stats.a_to_b.dropped, stats.a_to_b.bytes), (3, 1, 12));
+        assert_eq!((stats.b_to_a.frames, stats.b_to_a.dropped), (3, 0));
+    });
+
+    let server = thread::spawn(move || {
+        let mut receiver = ReceiverBuilder::buffered()
+            .with_type::<u32>()
+            .build(BufReader::new(server.try_clone().unwrap()));
+        let mut sender = SenderBuilder::buffered()
+            .with_type::<u32>()
+            .build(BufWriter::new(server));
+        // Echo every number, doubled.
+        for number in receiver.iter() {
+            sender.send(&(number.unwrap() * 2)).unwrap();
+            sender.flush().unwrap();
+        }
+    });
+
+    let mut sender = SenderBuilder::buffered()
+        .with_type::<u32>()
+        .build(BufWriter::new(client.try_clone().unwrap()));
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<u32>()
+        .build(BufReader::new(client.try_clone().unwrap()));
+
+    for number in &[1, 13, 2, 3] {
+        sender.send(number).unwrap();
+        sender.flush().unwrap();
+    }
+    for expected in &[2, 4, 6] {
+        assert_eq!(receiver.recv().unwrap(), *expected);
+    }
+
+    // Disconnecting the client closes the relay and the server connection.
+    client.shutdown(Shutdown::Write).unwrap();
+    match receiver.recv() {
+        Err(RecvError::Disconnected) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    relay.join().unwrap();
+    server.join().unwrap();
+}
+
+#[test]
+fn too_large() {
+    let (client, server, relay) = connect(Relay::new().with_max_size(16), |relay, a, b| {
+        match relay.run(a, b) {
+            Err(ForwardError::Recv(RecvError::TooLarge(_))) => (),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    });
+
+    let mut sender = SenderBuilder::buffered()
+        .with_type::<Vec<u8>>()
+        .build(BufWriter::new(client));
+    sender.send(&vec! [0; 64]).unwrap();
+    sender.flush().unwrap();
+    relay.join().unwrap();
+
+    // Nothing was forwarded, and the server connection is closed.
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<Vec<u8>>()
+        .build(BufReader::new(server));
+    match receiver.recv() {
+        Err(RecvError::Disconnected) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
@@ -0,0 +1,103 @@
+extern crate tcp_channel;
+extern crate serde;
+#[macro_use] extern crate serde_derive;
+
+use std::io::Cursor;
+
+use tcp_channel::{schema_fingerprint, ChannelRecv, ReceiverBuilder, RecvError, SenderBuilder};
+
+mod v1 {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    pub enum Message {
+        Ping,
+    }
+}
+mod v2 {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    pub enum Message {
+        Ping,
+        Pong,
+    }
+}
+
+fn encode(version: Option<u64>) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    {
+        let builder = SenderBuilder::buffered()
+            .with_type::<v1::Message>()
+            .with_writer::<&mut Vec<u8>>();
+        let builder = match version {
+            Some(version) => builder.with_schema_version(version),
+            None => builder.with_handshake(),
+        };
+        builder.build(&mut bytes).send_all([v1::Message::Ping]).unwrap();
+    }
+    bytes
+}
+
+#[test]
+fn matching_schema() {
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<v1::Message>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_schema_version(3)
+        .build(Cursor::new(encode(Some(3))));
+    assert_eq!(receiver.recv().unwrap(), v1::Message::Ping);
+
+    // An explicit fingerprint matches the derived one.
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<v1::Message>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_fingerprint(schema_fingerprint::<v1::Message>(3))
+        .build(Cursor::new(encode(Some(3))));
+    assert_eq!(receiver.recv().unwrap(), v1::Message::Ping);
+}
+
+#[test]
+fn mismatched_schema() {
+    let sent = schema_fingerprint::<v1::Message>(3);
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<v1::Message>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_schema_version(4)
+        .build(Cursor::new(encode(Some(3))));
+    match receiver.recv() {
+        Err(RecvError::SchemaMismatch(sender, receiver)) => {
+            assert_eq!(sender, Some(sent));
+            assert_eq!(receiver, schema_fingerprint::<v1::Message>(4));
+        },
+        result => panic!("unexpected result: {:?}", result),
+    }
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<v2::Message>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_schema_version(3)
+        .build(Cursor::new(encode(Some(3))));
+    match receiver.recv() {
+        Err(RecvError::SchemaMismatch(Some(sender), _)) => assert_eq!(sender, sent),
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn missing_schema() {
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<v1::Message>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_schema_version(3)
+        .build(Cursor::new(encode(None)));
+    match receiver.recv() {
+        Err(RecvError::SchemaMismatch(None, _)) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+
+    // A receiver that doesn't expect a schema ignores it.
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<v1::Message>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_handshake()
+        .build(Cursor::new(encode(Some(3))));
+    assert_eq!(receiver.recv().unwrap(), v1::Message::Ping);
+}
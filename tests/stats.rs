@@ -0,0 +1,97 @@
+extern crate tcp_channel;
+extern crate serde;
+
+use std::io::Cursor;
+
+use serde::ser::{Error, Serialize, Serializer};
+
+use tcp_channel::{ChannelRecv, ChannelSend, HeaderEncoding, ReceiverBuilder, ReceiverStats, RecvError, SendError, SenderBuilder, SenderStats};
+
+// A message that fails to serialize if it is `None`.
+struct Message(Option<u32>);
+impl Serialize for Message {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            Some(value) => value.serialize(serializer),
+            None => Err(S::Error::custom("no value")),
+        }
+    }
+}
+
+#[test]
+fn sender_stats() {
+    let mut bytes = Vec::new();
+    let mut sender = SenderBuilder::buffered()
+        .with_type::<Message>()
+        .with_writer::<&mut Vec<u8>>()
+        .with_header_encoding(HeaderEncoding::U16)
+        .with_max_size(8)
+        .with_handshake()
+        .build(&mut bytes);
+
+    sender.send(&Message(Some(1))).unwrap();
+    sender.send_batch(&[Message(Some(2)), Message(Some(3))]).unwrap();
+    sender.send_raw(&[0; 6]).unwrap();
+    match sender.send_raw(&[0; 9]) {
+        Err(SendError::TooLarge(9)) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    match sender.send(&Message(None)) {
+        Err(SendError::BincodeError(_)) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+
+    let stats = sender.stats();
+    assert_eq!(stats, SenderStats {
+        messages: 4,
+        header_bytes: 8,
+        payload_bytes: 18,
+        largest_frame: 6,
+        too_large: 1,
+        encode_errors: 1,
+        serialize_time: stats.serialize_time,
+    });
+}
+
+#[test]
+fn receiver_stats() {
+    let mut bytes = Vec::new();
+    {
+        let mut sender = SenderBuilder::buffered()
+            .with_type::<u32>()
+            .with_writer::<&mut Vec<u8>>()
+            .with_header_encoding(HeaderEncoding::Varint)
+            .build(&mut bytes);
+        sender.send(&1).unwrap();
+        sender.send_raw(&[1, 2]).unwrap();
+        sender.send_raw(&[0; 200]).unwrap();
+    }
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<u32>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_header_encoding(HeaderEncoding::Varint)
+        .with_max_size(100)
+        .build(Cursor::new(bytes));
+
+    assert_eq!(receiver.recv().unwrap(), 1);
+    match receiver.recv() {
+        Err(RecvError::BincodeError(..)) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    match receiver.recv() {
+        Err(RecvError::TooLarge(200)) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+
+    let stats = receiver.stats();
+    assert_eq!(stats, ReceiverStats {
+        messages: 2,
+        header_bytes: 2,
+        payload_bytes: 6,
+        largest_frame: 4,
+        too_large: 1,
+        decode_errors: 1,
+        deserialize_time: stats.deserialize_time,
+    });
+}
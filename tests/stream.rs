@@ -0,0 +1,147 @@
+extern crate tcp_channel;
+extern crate serde;
+#[macro_use] extern crate serde_derive;
+
+use std::io::{Cursor, ErrorKind as IoErrorKind, Read, Result as IoResult};
+
+use tcp_channel::{ChannelRecv, ChannelSend, ReceiverBuilder, SendError, SenderBuilder};
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum Message {
+    File { name: String, size: u64 },
+    Done,
+}
+
+fn file(size: usize) -> Vec<u8> {
+    (0..size).map(|index| (index % 251) as u8).collect()
+}
+
+fn encode(files: &[Vec<u8>]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    {
+        // The max size is far below the size of the files.
+        let mut sender = SenderBuilder::buffered()
+            .with_type::<Message>()
+            .with_writer::<&mut Vec<u8>>()
+            .with_max_size(4096)
+            .build(&mut bytes);
+
+        for (index, file) in files.iter().enumerate() {
+            sender.send(&Message::File { name: format!("file #{}", index), size: file.len() as u64 }).unwrap();
+            assert_eq!(sender.send_stream(&file[..]).unwrap(), file.len() as u64);
+        }
+        sender.send(&Message::Done).unwrap();
+    }
+    bytes
+}
+
+#[test]
+fn stream() {
+    let files = vec! [file(1_000_000), file(0), file(4096)];
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<Message>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_max_size(4096)
+        .build(Cursor::new(encode(&files)));
+
+    for (index, file) in files.iter().enumerate() {
+        let size = match receiver.recv().unwrap() {
+            Message::File { name, size } => {
+                assert_eq!(name, format!("file #{}", index));
+                size
+            },
+            other => panic!("unexpected message: {:?}", other),
+        };
+
+        let mut received = Vec::new();
+        receiver.recv_stream().unwrap().read_to_end(&mut received).unwrap();
+        assert_eq!(received.len() as u64, size);
+        assert!(&received == file);
+    }
+    assert_eq!(receiver.recv().unwrap(), Message::Done);
+}
+
+#[test]
+fn unread_stream_is_skipped() {
+    let files = vec! [file(100_000), file(10)];
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<Message>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_max_size(4096)
+        .build(Cursor::new(encode(&files)));
+
+    receiver.recv().unwrap();
+    // Only read the beginning of the first stream.
+    let mut beginning = [0; 100];
+    receiver.recv_stream().unwrap().read_exact(&mut beginning).unwrap();
+    assert_eq!(&beginning[..], &files[0][..100]);
+
+    match receiver.recv().unwrap() {
+        Message::File { size: 10, .. } => (),
+        other => panic!("unexpected message: {:?}", other),
+    }
+    // Don't read the second stream at all.
+    receiver.recv_stream().unwrap();
+    assert_eq!(receiver.recv().unwrap(), Message::Done);
+}
+
+// A source that fails once its data has been read.
+struct Failing<'a>(&'a [u8]);
+impl<'a> Read for Failing<'a> {
+    fn read(&mut self, buffer: &mut [u8]) -> IoResult<usize> {
+        if self.0.is_empty() {
+            return Err(IoErrorKind::Other.into())
+        }
+        self.0.read(buffer)
+    }
+}
+
+#[test]
+fn failing_source() {
+    let data = file(40);
+    let mut bytes = Vec::new();
+    {
+        let mut sender = SenderBuilder::buffered()
+            .with_type::<Message>()
+            .with_writer::<&mut Vec<u8>>()
+            .with_max_size(16)
+            .build(&mut bytes);
+        match sender.send_stream(Failing(&data)) {
+            Err(SendError::IoError(ref error)) if error.kind() == IoErrorKind::Other => (),
+            result => panic!("unexpected result: {:?}", result),
+        }
+        sender.send(&Message::Done).unwrap();
+    }
+
+    // The stream ends after what was read, and the channel stays in sync.
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<Message>()
+        .with_reader::<Cursor<Vec<u8>>>()
+        .with_max_size(16)
+        .build(Cursor::new(bytes));
+    let mut received = Vec::new();
+    receiver.recv_stream().unwrap().read_to_end(&mut received).unwrap();
+    assert_eq!(received, data);
+    assert_eq!(receiver.recv().unwrap(), Message::Done);
+}
+
+#[test]
+fn zero_max_size() {
+    let mut bytes = Vec::new();
+    let mut sender = SenderBuilder::buffered()
+        .with_type::<Message>()
+        .with_writer::<&mut Vec<u8>>()
+        .with_max_size(0)
+        .build(&mut bytes);
+
+    let mut source = Cursor::new(file(10));
+    match sender.send_stream(&mut source) {
+        Err(SendError::ZeroMaxSize) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    assert_eq!(source.position(), 0);
+    drop(sender);
+    assert!(bytes.is_empty());
+}
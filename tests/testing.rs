@@ -0,0 +1,99 @@
+extern crate tcp_channel;
+
+mod common;
+
+use std::io::{Cursor, ErrorKind as IoErrorKind, Read, Write};
+
+use tcp_channel::testing::{Faults, FaultyStream};
+use tcp_channel::{ChannelRecv, ReceiverBuilder, RecvError};
+
+use common::encode;
+
+// Read everything, retrying on the faults that don't end the stream.
+fn read_all<R: Read>(mut reader: R) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut buffer = [0; 64];
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => return bytes,
+            Ok(size) => bytes.extend_from_slice(&buffer[..size]),
+            Err(ref error) if error.kind() == IoErrorKind::WouldBlock || error.kind() == IoErrorKind::Interrupted => (),
+            Err(error) => panic!("unexpected error: {}", error),
+        }
+    }
+}
+
+#[test]
+fn short_reads_and_would_block() {
+    let values = (0..100).collect::<Vec<u32>>();
+    let faults = Faults::new(1).with_short_io(0.5).with_would_block(0.2).with_interrupted(0.1);
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<u32>()
+        .with_reader::<FaultyStream<Cursor<Vec<u8>>>>()
+        .build(FaultyStream::new(Cursor::new(encode(&values)), faults));
+
+    let mut received = Vec::new();
+    let mut faults = 0;
+    loop {
+        match receiver.recv() {
+            Ok(value) => received.push(value),
+            Err(RecvError::Disconnected) => break,
+            Err(RecvError::IoError(ref error)) if error.kind() == IoErrorKind::WouldBlock || error.kind() == IoErrorKind::Interrupted => faults += 1,
+            Err(error) => panic!("unexpected error: {:?}", error),
+        }
+    }
+    assert_eq!(received, values);
+    assert!(faults > 0);
+}
+
+#[test]
+fn reproducible() {
+    let bytes = (0..=255).collect::<Vec<u8>>();
+    let faults = Faults::new(42).with_short_io(0.5).with_bit_flips(0.1);
+
+    let first = read_all(FaultyStream::new(&bytes[..], faults));
+    let second = read_all(FaultyStream::new(&bytes[..], faults));
+    assert_eq!(first, second);
+    assert_eq!(first.len(), bytes.len());
+    assert_ne!(first, bytes);
+
+    let other = read_all(FaultyStream::new(&bytes[..], Faults::new(43).with_short_io(0.5).with_bit_flips(0.1)));
+    assert_ne!(first, other);
+}
+
+#[test]
+fn truncate_and_disconnect() {
+    let bytes = encode::<u32>(&[1, 2, 3]);
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<u32>()
+        .with_reader::<FaultyStream<Cursor<Vec<u8>>>>()
+        .build(FaultyStream::new(Cursor::new(bytes.clone()), Faults::new(0).truncate_at(18)));
+    assert_eq!(receiver.recv().unwrap(), 1);
+    match receiver.recv() {
+        Err(RecvError::IoError(ref error)) if error.kind() == IoErrorKind::UnexpectedEof => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+
+    let mut receiver = ReceiverBuilder::buffered()
+        .with_type::<u32>()
+        .with_reader::<FaultyStream<Cursor<Vec<u8>>>>()
+        .build(FaultyStream::new(Cursor::new(bytes), Faults::new(0).disconnect_at(12)));
+    assert_eq!(receiver.recv().unwrap(), 1);
+    match receiver.recv() {
+        Err(RecvError::IoError(ref error)) if error.kind() == IoErrorKind::ConnectionReset => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+
+    // Writes past the truncation succeed, but go nowhere.
+    let mut writer = FaultyStream::new(Vec::new(), Faults::new(0).truncate_at(4));
+    writer.write_all(&[1, 2, 3, 4, 5, 6]).unwrap();
+    assert_eq!(writer.into_inner(), [1, 2, 3, 4]);
+
+    let mut writer = FaultyStream::new(Vec::new(), Faults::new(0).disconnect_at(4));
+    match writer.write_all(&[1, 2, 3, 4, 5, 6]) {
+        Err(ref error) if error.kind() == IoErrorKind::BrokenPipe => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    assert_eq!(writer.offset(), 4);
+}
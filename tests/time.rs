@@ -12,6 +12,7 @@ use std::time::{SystemTime, Duration};
 
 use tcp_channel::{SenderBuilder, ReceiverBuilder, ChannelSend, ChannelRecv, BigEndian};
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
 enum Request {
     RequestTime,
@@ -42,7 +43,7 @@ quick_error! {
         RecvErr2(err: tcp_channel::RecvError) {
             from()
         }
-        JoinErr(err: Box<Any + Send + 'static>) {
+        JoinErr(err: Box<dyn Any + Send + 'static>) {
             from()
         }
     }
@@ -0,0 +1,122 @@
+#![cfg(feature = "tracing")]
+
+extern crate tcp_channel;
+extern crate tracing;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Cursor;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+use tcp_channel::{ChannelRecv, ChannelSend, ReceiverBuilder, SenderBuilder};
+
+#[derive(Default)]
+struct Fields(HashMap<String, String>);
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().into(), format!("{:?}", value));
+    }
+}
+
+struct Span {
+    name: &'static str,
+    fields: Fields,
+}
+struct Recorded {
+    span: Option<usize>,
+    fields: Fields,
+}
+
+// Records every span and event, along with the span each event was emitted in.
+#[derive(Clone, Default)]
+struct Recorder {
+    spans: Arc<Mutex<Vec<Span>>>,
+    entered: Arc<Mutex<Vec<usize>>>,
+    events: Arc<Mutex<Vec<Recorded>>>,
+}
+impl Recorder {
+    // The fields of the only event with this message, and the span it was emitted in.
+    fn event(&self, message: &str) -> (&'static str, HashMap<String, String>, HashMap<String, String>) {
+        let spans = self.spans.lock().unwrap();
+        let events = self.events.lock().unwrap();
+        let mut matching = events.iter().filter(|event| event.fields.0.get("message").map(String::as_str) == Some(message));
+        let event = matching.next().unwrap_or_else(|| panic!("no event {:?}", message));
+        assert!(matching.next().is_none(), "more than one event {:?}", message);
+
+        let span = &spans[event.span.expect("event outside of a span")];
+        (span.name, span.fields.0.clone(), event.fields.0.clone())
+    }
+}
+impl Subscriber for Recorder {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+    fn new_span(&self, attributes: &Attributes) -> Id {
+        let mut fields = Fields::default();
+        attributes.record(&mut fields);
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(Span { name: attributes.metadata().name(), fields });
+        Id::from_u64(spans.len() as u64)
+    }
+    fn record(&self, _span: &Id, _values: &Record) {}
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+    fn event(&self, event: &Event) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let span = self.entered.lock().unwrap().last().cloned();
+        self.events.lock().unwrap().push(Recorded { span, fields });
+    }
+    fn enter(&self, span: &Id) {
+        self.entered.lock().unwrap().push(span.into_u64() as usize - 1);
+    }
+    fn exit(&self, _span: &Id) {
+        self.entered.lock().unwrap().pop();
+    }
+}
+
+#[test]
+fn frames() {
+    let recorder = Recorder::default();
+    let peer: SocketAddr = "192.0.2.1:4000".parse().unwrap();
+
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let mut bytes = Vec::new();
+        SenderBuilder::buffered()
+            .with_type::<u32>()
+            .with_writer::<&mut Vec<u8>>()
+            .with_peer_addr(peer)
+            .build(&mut bytes)
+            .send(&42)
+            .unwrap();
+
+        let mut receiver = ReceiverBuilder::buffered()
+            .with_type::<u32>()
+            .with_reader::<Cursor<Vec<u8>>>()
+            .with_peer_addr(peer)
+            .build(Cursor::new(bytes));
+        assert_eq!(receiver.recv().unwrap(), 42);
+    });
+
+    let expected = [
+        ("send", "serialized message", "size", "12"),
+        ("send", "wrote frames", "bytes", "12"),
+        ("recv", "received frame", "size", "4"),
+        ("recv", "deserialized message", "size", "4"),
+    ];
+    for &(span, message, size_field, size) in &expected {
+        let (name, span_fields, fields) = recorder.event(message);
+        assert_eq!(name, span, "span of {:?}", message);
+        assert_eq!(span_fields["message"], "u32");
+        assert_eq!(span_fields["peer"], format!("{:?}", Some(peer)));
+        assert_eq!(fields[size_field], size, "size in {:?}", message);
+        assert!(fields.contains_key("elapsed"), "no duration in {:?}", message);
+    }
+}